@@ -0,0 +1,23 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use changelog::{Changelog, Kind};
+
+// The input is a changelog, a NUL byte and the text of an entry, whose
+// kind is chosen by the text's length.
+fuzz_target!(|input: &str| {
+    let mut parts = input.splitn(2, '\0');
+    let (document, text) = match (parts.next(), parts.next()) {
+        (Some(document), Some(text)) => (document, text),
+        _ => return,
+    };
+    let kind = Kind::ALL[text.len() % Kind::ALL.len()];
+
+    if let Ok(mut changelog) = Changelog::parse(document) {
+        if changelog.add_entry(kind, text).is_ok() {
+            let written = changelog.to_string();
+            let reparsed = Changelog::parse(&written).expect("written changelog does not parse");
+            assert_eq!(changelog, reparsed, "written:\n{}", written);
+        }
+    }
+});
@@ -0,0 +1,8 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use changelog::Changelog;
+
+fuzz_target!(|input: &str| {
+    let _ = Changelog::parse(input);
+});
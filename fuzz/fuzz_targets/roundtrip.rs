@@ -0,0 +1,14 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+use changelog::Changelog;
+
+// Whatever parses is written back in a form that parses to the same
+// document.
+fuzz_target!(|input: &str| {
+    if let Ok(changelog) = Changelog::parse(input) {
+        let written = changelog.to_string();
+        let reparsed = Changelog::parse(&written).expect("written changelog does not parse");
+        assert_eq!(changelog, reparsed, "written:\n{}", written);
+    }
+});
@@ -0,0 +1,333 @@
+//! AsciiDoc renderer that takes an iterator of events as input.
+//!
+//! The blocks of a list item after its first are attached to it with a list
+//! continuation, `+`, as AsciiDoc lists are not nested by indentation.
+//!
+//! Text that AsciiDoc would read as markup, like `*stars*` or `{name}`, is
+//! passed through, and inline HTML is left out.
+
+use std::io::{self, Write};
+
+use pulldown_cmark::Event::*;
+use pulldown_cmark::{CodeBlockKind, Event, LinkType, Tag};
+
+use crate::render::{Output, StrWrite, WriteWrapper};
+
+/// Characters of text that AsciiDoc could read as markup, an attribute
+/// reference, a cross reference or the end of a macro.
+const MARKUP: &[char] = &['*', '_', '`', '#', '+', '^', '~', '{', '[', ']', '<', '>'];
+
+/// Text as it is in AsciiDoc: the parts with markup characters in a
+/// `pass:c[]` macro, which only escapes `<`, `>` and `&`, and backslashes,
+/// which would escape its end, as `{backslash}`.
+fn escape(text: &str) -> String {
+    text.split('\\')
+        .map(|part| {
+            if part.contains(MARKUP) {
+                format!("pass:c[{}]", part.replace(']', "\\]"))
+            } else {
+                part.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("{backslash}")
+}
+
+struct AsciidocWriter<I, W> {
+    /// Iterator supplying events.
+    iter: I,
+
+    out: Output<W>,
+
+    /// Whether the last event was a block of raw HTML.
+    html_block: bool,
+
+    /// Whether each open list is ordered.
+    lists: Vec<bool>,
+
+    /// Text of the last text events, escaped as a whole.
+    text: String,
+}
+
+impl<'a, I, W> AsciidocWriter<I, W>
+where
+    I: Iterator<Item = Event<'a>>,
+    W: StrWrite,
+{
+    fn new(iter: I, writer: W) -> Self {
+        Self {
+            iter,
+            out: Output::new(writer),
+            html_block: false,
+            lists: vec![],
+            text: String::new(),
+        }
+    }
+
+    /// Moves to a new block, attaching it to the open list item if any.
+    fn start_block(&mut self) -> io::Result<()> {
+        if !self.lists.is_empty() && self.out.end_block && !self.out.after_marker {
+            self.out.end_line()?;
+            self.out.write("+\n")?;
+            self.out.end_block = false;
+        }
+        self.out.start_block()
+    }
+
+    /// Writes the text of the last text events.
+    fn write_text(&mut self) -> io::Result<()> {
+        if !self.text.is_empty() {
+            let text = escape(&self.text);
+            self.text.clear();
+            self.out.write(&text)?;
+        }
+        Ok(())
+    }
+
+    pub fn run(mut self) -> io::Result<()> {
+        while let Some(event) = self.iter.next() {
+            if self.html_block && !matches!(event, Html(_)) {
+                self.html_block = false;
+                self.out.end_line()?;
+                self.out.write("++++")?;
+                self.out.end_block()?;
+            }
+            if !matches!(event, Text(_)) {
+                self.write_text()?;
+            }
+            match event {
+                Start(tag) => {
+                    self.start_tag(tag)?;
+                }
+                End(tag) => {
+                    self.end_tag(tag)?;
+                }
+                Text(text) => {
+                    self.text.push_str(&text);
+                }
+                Code(text) => {
+                    // a passthrough keeps the code from being formatted
+                    if text.contains(&['+', '\\'][..]) {
+                        self.out.write(&format!("`{}`", escape(&text)))?;
+                    } else {
+                        self.out.write(&format!("`+{}+`", text))?;
+                    }
+                }
+                Html(html) => {
+                    // blocks are passed through, inline HTML left out
+                    if html.ends_with('\n') && !self.html_block {
+                        self.start_block()?;
+                        self.out.write("++++\n")?;
+                        self.html_block = true;
+                    }
+                    if self.html_block {
+                        self.out.write(&html)?;
+                    }
+                }
+                SoftBreak => {
+                    self.out.write_newline()?;
+                }
+                HardBreak => {
+                    self.out.write(" +")?;
+                    self.out.write_newline()?;
+                }
+                Rule => {
+                    self.start_block()?;
+                    self.out.write("'''")?;
+                    self.out.end_block()?;
+                }
+                FootnoteReference(name) => {
+                    self.out.write(&format!("footnote:{}[]", name))?;
+                }
+                TaskListMarker(true) => {
+                    self.out.write("[x] ")?;
+                }
+                TaskListMarker(false) => {
+                    self.out.write("[ ] ")?;
+                }
+            }
+        }
+        self.write_text()?;
+        if self.html_block {
+            self.out.end_line()?;
+            self.out.write("++++")?;
+            self.out.end_block()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the start of an AsciiDoc element.
+    fn start_tag(&mut self, tag: Tag<'a>) -> io::Result<()> {
+        match tag {
+            Tag::Paragraph => self.start_block(),
+            Tag::Heading(level, id, _) => {
+                self.start_block()?;
+                if let Some(id) = id {
+                    self.out.write(&format!("[#{}]\n", id))?;
+                }
+                let heading = "=".repeat(level as usize);
+                self.out.write(&format!("{} ", heading))
+            }
+            Tag::Table(_) => {
+                self.start_block()?;
+                self.out.write("|===\n")
+            }
+            Tag::TableHead | Tag::TableRow => Ok(()),
+            Tag::TableCell => self.out.write("|"),
+            Tag::BlockQuote => {
+                self.start_block()?;
+                self.out.write("____")?;
+                self.out.end_line()
+            }
+            Tag::CodeBlock(info) => {
+                self.start_block()?;
+                if let CodeBlockKind::Fenced(info) = info {
+                    if let Some(language) = info.split_whitespace().next() {
+                        self.out.write(&format!("[source,{}]\n", language))?;
+                    }
+                }
+                self.out.write("----\n")
+            }
+            Tag::List(start) => {
+                self.start_block()?;
+                self.lists.push(start.is_some());
+                Ok(())
+            }
+            Tag::Item => {
+                self.out.end_line()?;
+                self.out.end_block = false;
+                let marker = match self.lists.last() {
+                    Some(true) => ".",
+                    _ => "*",
+                };
+                self.out
+                    .write(&format!("{} ", marker.repeat(self.lists.len())))?;
+                self.out.after_marker = true;
+                Ok(())
+            }
+            // unconstrained markup, which also works inside words
+            Tag::Emphasis => self.out.write("__"),
+            Tag::Strong => self.out.write("**"),
+            Tag::Strikethrough => self.out.write("[line-through]##"),
+            Tag::Link(LinkType::Autolink, _, _) | Tag::Link(LinkType::Email, _, _) => Ok(()),
+            Tag::Link(_, dest, _) => self.out.write(&format!("link:{}[", dest)),
+            Tag::Image(_, dest, _) => self.out.write(&format!("image:{}[", dest)),
+            Tag::FootnoteDefinition(name) => {
+                self.start_block()?;
+                self.out.write(&format!("[{}] ", name))
+            }
+        }
+    }
+
+    fn end_tag(&mut self, tag: Tag) -> io::Result<()> {
+        match tag {
+            Tag::Paragraph | Tag::Heading(..) | Tag::FootnoteDefinition(_) => {
+                self.out.end_block()?;
+            }
+            Tag::Table(_) => {
+                self.out.write("|===")?;
+                self.out.end_block()?;
+            }
+            Tag::TableHead => {
+                // a blank line after the first row makes it the header
+                self.out.write_newline()?;
+                self.out.write_newline()?;
+            }
+            Tag::TableRow => {
+                self.out.write_newline()?;
+            }
+            Tag::TableCell => {
+                self.out.write(" ")?;
+            }
+            Tag::BlockQuote => {
+                self.out.end_line()?;
+                self.out.write("____")?;
+                self.out.end_block()?;
+            }
+            Tag::CodeBlock(_) => {
+                self.out.end_line()?;
+                self.out.write("----")?;
+                self.out.end_block()?;
+            }
+            Tag::List(_) => {
+                self.lists.pop();
+                self.out.end_block()?;
+            }
+            Tag::Item => {
+                self.out.after_marker = false;
+                self.out.end_line()?;
+            }
+            Tag::Emphasis => {
+                self.out.write("__")?;
+            }
+            Tag::Strong => {
+                self.out.write("**")?;
+            }
+            Tag::Strikethrough => {
+                self.out.write("##")?;
+            }
+            Tag::Link(LinkType::Autolink, _, _) | Tag::Link(LinkType::Email, _, _) => (),
+            Tag::Link(..) | Tag::Image(..) => {
+                self.out.write("]")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Iterate over an `Iterator` of `Event`s, generate AsciiDoc for each
+/// `Event`, and write it out to a writable stream.
+pub(crate) fn write<'a, I, W>(writer: W, iter: I) -> io::Result<()>
+where
+    I: Iterator<Item = Event<'a>>,
+    W: Write,
+{
+    AsciidocWriter::new(iter, WriteWrapper(writer)).run()
+}
+
+#[cfg(test)]
+mod tests {
+    use pulldown_cmark::Parser;
+
+    fn asciidoc(markdown: &str) -> String {
+        let mut out = vec![];
+        super::write(&mut out, Parser::new(markdown)).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn markup_characters_are_passed_through() {
+        assert_eq!(
+            asciidoc("\\*stars\\* and {attr}, a+b, snake_case, #1, ^x^ ~y~\n"),
+            "pass:c[*stars* and {attr}, a+b, snake_case, #1, ^x^ ~y~]\n"
+        );
+        assert_eq!(asciidoc("[[x]] \\<\\<y>>\n"), "pass:c[[[x\\]\\] <<y>>]\n");
+        assert_eq!(
+            asciidoc("No markup & no escape.\n"),
+            "No markup & no escape.\n"
+        );
+    }
+
+    #[test]
+    fn backslashes_are_attributes() {
+        assert_eq!(asciidoc("C:\\\\dir\\\\\n"), "C:{backslash}dir{backslash}\n");
+        assert_eq!(asciidoc("a\\\\_b\n"), "a{backslash}pass:c[_b]\n");
+    }
+
+    #[test]
+    fn links_and_code() {
+        assert_eq!(
+            asciidoc("[a \\] *b*](https://example.com) `x+` `a\\` `c`\n"),
+            "link:https://example.com[pass:c[a \\] ]__b__] `pass:c[x+]` `a{backslash}` `+c+`\n"
+        );
+    }
+
+    #[test]
+    fn inline_html_is_left_out() {
+        assert_eq!(asciidoc("a <b>bold</b> c\n"), "a bold c\n");
+        assert_eq!(
+            asciidoc("<div>\n*block*\n</div>\n\nafter\n"),
+            "++++\n<div>\n*block*\n</div>\n++++\n\nafter\n"
+        );
+    }
+}
@@ -0,0 +1,122 @@
+//! Backport of entries from the changelog of another branch, e.g. when a fix
+//! is cherry-picked to a maintenance branch.
+//!
+//! An entry is looked for by its id, as `changelog entries` lists it, or by
+//! its text: the same text first, then the entries containing it, ignoring
+//! case.
+
+use crate::changelog::{Changelog, Entry, Kind, Release};
+use crate::error::ChangelogError;
+
+impl Changelog {
+    /// Finds the entry `query` is the id or the text of, with its release
+    /// and the kind of its section.
+    ///
+    /// An entry repeated in several releases is the one of the newest, and
+    /// a query matching different entries is an error listing their ids.
+    pub fn find_entry(&self, query: &str) -> Result<(&Release, Kind, &Entry), ChangelogError> {
+        let query = query.trim();
+        let entries: Vec<(&Release, Option<Kind>, &Entry)> = self
+            .releases
+            .iter()
+            .flat_map(|release| {
+                release.sections.iter().flat_map(move |section| {
+                    section
+                        .entries
+                        .iter()
+                        .map(move |entry| (release, section.kind, entry))
+                })
+            })
+            .collect();
+
+        // ids are looked up as for the entries of a release, in the newest
+        // release first
+        let by_id = (0..self.releases.len()).find_map(|position| {
+            let (i, j) = self.entry_position(position, query).ok()?;
+            let release = &self.releases[position];
+            let section = &release.sections[i];
+            Some((release, section.kind, &section.entries[j]))
+        });
+        let lowercase = query.to_lowercase();
+        let matches: [&dyn Fn(&Entry) -> bool; 2] = [&|entry| entry.text == query, &|entry| {
+            entry.text.to_lowercase().contains(&lowercase)
+        }];
+        let found: Vec<(&Release, Option<Kind>, &Entry)> = match by_id {
+            Some(found) => vec![found],
+            None => matches
+                .iter()
+                .map(|matches| {
+                    entries
+                        .iter()
+                        .copied()
+                        .filter(|(_, _, entry)| matches(entry))
+                        .collect::<Vec<_>>()
+                })
+                .find(|found| !found.is_empty())
+                .ok_or_else(|| ChangelogError::NotFound(format!("no entry matches '{}'", query)))?,
+        };
+
+        // the same entry in several releases is not ambiguous
+        let mut ids: Vec<String> = found.iter().map(|(_, _, entry)| entry.id()).collect();
+        ids.sort();
+        ids.dedup();
+        if ids.len() > 1 {
+            return Err(ChangelogError::Validation(format!(
+                "'{}' matches {} entries: {}",
+                query,
+                ids.len(),
+                ids.join(", ")
+            )));
+        }
+
+        let (release, kind, entry) = found[0];
+        let kind = kind.ok_or_else(|| {
+            ChangelogError::Validation(format!(
+                "entry {} is in a section of {} that is not a standard one",
+                entry.id(),
+                release.version
+            ))
+        })?;
+        Ok((release, kind, entry))
+    }
+
+    /// Adds the entry of `from` that `query` finds to the Unreleased
+    /// changes of `line`, under the same kind of section, and returns it.
+    ///
+    /// The entry ends with `(backport of <reference>)` if there is one, e.g.
+    /// the commit cherry-picked. It is a conflict for the entry to be in
+    /// this changelog already.
+    pub fn backport(
+        &mut self,
+        from: &Changelog,
+        query: &str,
+        reference: Option<&str>,
+        line: Option<&str>,
+    ) -> Result<Entry, ChangelogError> {
+        let (_, kind, entry) = from.find_entry(query)?;
+
+        // a backport already made has the text with its reference
+        let backported = format!("{} (backport of ", entry.text);
+        let existing = self.releases.iter().find(|release| {
+            release
+                .sections
+                .iter()
+                .flat_map(|section| &section.entries)
+                .any(|e| e.text == entry.text || e.text.starts_with(&backported))
+        });
+        if let Some(release) = existing {
+            return Err(ChangelogError::Conflict(format!(
+                "entry {} is already in {}",
+                entry.id(),
+                release.version
+            )));
+        }
+
+        let text = match reference.map(str::trim).filter(|r| !r.is_empty()) {
+            Some(reference) => format!("{} (backport of {})", entry.text, reference),
+            None => entry.text.clone(),
+        };
+        self.add_line_entry(kind, &text, line)?;
+        Ok(Entry::new(&text))
+    }
+}
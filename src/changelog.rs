@@ -0,0 +1,1584 @@
+//! Document model of a Keep a Changelog file.
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use pulldown_cmark::{BrokenLink, CowStr, Event, HeadingLevel, Options, Parser, Tag};
+use regex::Regex;
+
+use crate::date::Date;
+use crate::error::ChangelogError;
+use crate::locale::Locale;
+use crate::markdown;
+use crate::parse;
+use crate::schema::plain_text;
+use crate::version::Version;
+
+const HEADING_RELEASE: HeadingLevel = HeadingLevel::H2;
+const HEADING_SECTION: HeadingLevel = HeadingLevel::H3;
+
+const PREAMBLE: &str = "# Changelog
+
+All notable changes to this project will be documented in this file.
+
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.0.0/),
+and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).";
+
+/// Kind of change, one per standard section.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    Added,
+    Changed,
+    Deprecated,
+    Removed,
+    Fixed,
+    Security,
+}
+
+impl Kind {
+    /// All kinds, in the order their sections are written.
+    pub const ALL: [Kind; 6] = [
+        Kind::Added,
+        Kind::Changed,
+        Kind::Deprecated,
+        Kind::Removed,
+        Kind::Fixed,
+        Kind::Security,
+    ];
+
+    /// Lowercase name, which is also the subcommand adding such an entry.
+    pub fn name(self) -> &'static str {
+        [
+            "added",
+            "changed",
+            "deprecated",
+            "removed",
+            "fixed",
+            "security",
+        ][self as usize]
+    }
+}
+
+impl FromStr for Kind {
+    type Err = ChangelogError;
+
+    fn from_str(s: &str) -> Result<Kind, ChangelogError> {
+        Kind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ChangelogError::Validation(format!("unknown entry kind '{}'", s)))
+    }
+}
+
+/// A bullet of a section.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    /// Markdown of the bullet, without its list marker.
+    pub text: String,
+}
+
+impl Entry {
+    /// Short id derived from the text, so that it stays the same as long as
+    /// the text does.
+    pub fn id(&self) -> String {
+        // 32-bit FNV-1a
+        let hash = self.text.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+            (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+        });
+        format!("{:08x}", hash)
+    }
+
+    pub fn new(text: &str) -> Entry {
+        Entry {
+            text: text
+                .replace("\r\n", "\n")
+                .replace('\r', "\n")
+                .trim_matches(&[' ', '\t', '\n'][..])
+                .to_string(),
+        }
+    }
+}
+
+/// A `###` section of a release.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Section {
+    /// Heading as written in the file.
+    pub title: String,
+
+    /// Kind of the heading, `None` if it is not a standard one.
+    pub kind: Option<Kind>,
+
+    /// Markdown in the section that is not part of its list.
+    pub description: String,
+
+    pub entries: Vec<Entry>,
+}
+
+impl Section {
+    pub fn new(kind: Kind, locale: &Locale) -> Section {
+        Section {
+            title: locale.heading(kind).to_string(),
+            kind: Some(kind),
+            description: String::new(),
+            entries: vec![],
+        }
+    }
+}
+
+/// How the version in a release heading is written.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VersionStyle {
+    /// `[1.0.0]`, linked by a reference definition if any.
+    Bracketed,
+
+    /// `[1.0.0](https://...)`, with its URL.
+    Linked(String),
+
+    /// `1.0.0`.
+    Plain,
+}
+
+/// How the date in a release heading is set apart from the version.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DateStyle {
+    /// `1.0.0 - 2020-01-01`.
+    Hyphen,
+
+    /// `1.0.0 – 2020-01-01`.
+    EnDash,
+
+    /// `1.0.0 — 2020-01-01`.
+    EmDash,
+
+    /// `1.0.0 (2020-01-01)`.
+    Parenthesized,
+}
+
+impl DateStyle {
+    pub const ALL: [DateStyle; 4] = [
+        DateStyle::Hyphen,
+        DateStyle::EnDash,
+        DateStyle::EmDash,
+        DateStyle::Parenthesized,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DateStyle::Hyphen => "hyphen",
+            DateStyle::EnDash => "en-dash",
+            DateStyle::EmDash => "em-dash",
+            DateStyle::Parenthesized => "parenthesized",
+        }
+    }
+}
+
+impl FromStr for DateStyle {
+    type Err = ChangelogError;
+
+    fn from_str(s: &str) -> Result<DateStyle, ChangelogError> {
+        DateStyle::ALL
+            .iter()
+            .copied()
+            .find(|style| style.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ChangelogError::Validation(format!("unknown date style '{}'", s)))
+    }
+}
+
+/// How a release heading is written, so that it is written back the way
+/// it was read. The default is the style of Keep a Changelog,
+/// `[1.0.0] - 2020-01-01`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeadingStyle {
+    pub version: VersionStyle,
+    pub date: DateStyle,
+}
+
+impl Default for HeadingStyle {
+    fn default() -> Self {
+        HeadingStyle {
+            version: VersionStyle::Bracketed,
+            date: DateStyle::Hyphen,
+        }
+    }
+}
+
+/// A `##` release, or the upcoming changes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Release {
+    /// Version as written in the heading, or the Unreleased label.
+    pub version: String,
+
+    /// Release date, as written in the heading.
+    pub date: Option<String>,
+
+    /// Whether this is the block of upcoming changes.
+    pub unreleased: bool,
+
+    pub yanked: bool,
+
+    /// Markdown between the heading and the first section.
+    pub description: String,
+
+    pub sections: Vec<Section>,
+
+    /// How the heading is written.
+    pub style: HeadingStyle,
+}
+
+impl Release {
+    /// An empty block of upcoming changes.
+    pub fn unreleased(locale: &Locale) -> Release {
+        Release {
+            version: locale.unreleased.to_string(),
+            date: None,
+            unreleased: true,
+            yanked: false,
+            description: String::new(),
+            sections: vec![],
+            style: HeadingStyle::default(),
+        }
+    }
+
+    /// Section of a given kind.
+    pub fn section(&self, kind: Kind) -> Option<&Section> {
+        self.sections.iter().find(|s| s.kind == Some(kind))
+    }
+
+    /// Adds an entry to the section of its kind, creating the section in
+    /// the order of the kinds if there is none.
+    pub(crate) fn push_entry(&mut self, kind: Kind, entry: Entry, locale: &Locale) {
+        let index = match self.sections.iter().position(|s| s.kind == Some(kind)) {
+            Some(index) => index,
+            None => {
+                let index = self
+                    .sections
+                    .iter()
+                    .position(|s| s.kind.is_some_and(|k| k > kind))
+                    .unwrap_or(self.sections.len());
+                self.sections.insert(index, Section::new(kind, locale));
+                index
+            }
+        };
+        self.sections[index].entries.push(entry);
+    }
+
+    /// Heading text, without the leading `##`.
+    pub fn heading(&self) -> String {
+        let mut heading = match &self.style.version {
+            VersionStyle::Bracketed => format!("[{}]", self.version),
+            VersionStyle::Linked(url) => format!("[{}]({})", self.version, url),
+            VersionStyle::Plain => self.version.clone(),
+        };
+        if let Some(date) = &self.date {
+            heading.push_str(&match self.style.date {
+                DateStyle::Hyphen => format!(" - {}", date),
+                DateStyle::EnDash => format!(" \u{2013} {}", date),
+                DateStyle::EmDash => format!(" \u{2014} {}", date),
+                DateStyle::Parenthesized => format!(" ({})", date),
+            });
+        }
+        if self.yanked {
+            heading.push_str(" [YANKED]");
+        }
+        heading
+    }
+
+    /// Markdown of the description and sections, as they are written in
+    /// the changelog below the heading.
+    pub fn body(&self) -> String {
+        let mut events = vec![];
+        release_events(self, &raw_events, &mut events);
+        let mut buffer = Vec::new();
+        // writing to memory does not fail
+        markdown::write(&mut buffer, events.into_iter()).unwrap();
+        String::from_utf8_lossy(&buffer).into_owned()
+    }
+
+    /// Version, marked when the release is yanked.
+    pub fn title(&self) -> String {
+        if self.yanked {
+            format!("{} [YANKED]", self.version)
+        } else {
+            self.version.clone()
+        }
+    }
+
+    /// Id of the heading's anchor as GitHub renders it, e.g.
+    /// `100---2017-06-20` for `[1.0.0] - 2017-06-20`.
+    pub fn anchor(&self) -> String {
+        plain_text(&self.heading())
+            .to_lowercase()
+            .chars()
+            .filter_map(|c| match c {
+                ' ' => Some('-'),
+                c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sections.iter().all(|s| s.entries.is_empty())
+    }
+}
+
+/// Links of the Unreleased release and of the new `version` from a link of
+/// the Unreleased one following the `.../compare/<tag>...HEAD` convention,
+/// or `.../compare/<tag>...<branch>` for a maintenance line.
+fn compare_urls(unreleased: &str, version: &str) -> Option<(String, String)> {
+    lazy_static! {
+        static ref COMPARE_PATTERN: Regex =
+            Regex::new(r"^(?P<base>.*/compare/)(?P<previous>.+?)\.\.\.(?P<head>[^./][^/]*)$")
+                .unwrap();
+    }
+
+    let captures = COMPARE_PATTERN.captures(unreleased)?;
+    let (base, previous, head) = (&captures["base"], &captures["previous"], &captures["head"]);
+
+    // tags keep the prefix of the previous one, e.g. "v"
+    let prefix = previous
+        .find(|c: char| c.is_ascii_digit())
+        .map_or("", |p| &previous[..p]);
+    let tag = format!("{}{}", prefix, version);
+
+    Some((
+        format!("{}{}...{}", base, tag, head),
+        format!("{}{}...{}", base, previous, tag),
+    ))
+}
+
+/// Where a new release goes among the others.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReleaseOrder {
+    /// Newest first: right below the Unreleased releases.
+    Date,
+
+    /// Highest version first, so that the releases of each maintenance line
+    /// stay together.
+    Version,
+}
+
+impl ReleaseOrder {
+    pub const ALL: [ReleaseOrder; 2] = [ReleaseOrder::Date, ReleaseOrder::Version];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ReleaseOrder::Date => "date",
+            ReleaseOrder::Version => "version",
+        }
+    }
+}
+
+impl FromStr for ReleaseOrder {
+    type Err = ChangelogError;
+
+    fn from_str(s: &str) -> Result<ReleaseOrder, ChangelogError> {
+        ReleaseOrder::ALL
+            .iter()
+            .copied()
+            .find(|order| order.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ChangelogError::Validation(format!("unknown release order '{}'", s)))
+    }
+}
+
+/// How `Changelog::release_with` makes a release.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReleaseOptions {
+    /// Maintenance line released, e.g. `1.x`; by default the line the
+    /// version matches, or the main one.
+    pub line: Option<String>,
+
+    pub order: ReleaseOrder,
+}
+
+impl Default for ReleaseOptions {
+    fn default() -> Self {
+        ReleaseOptions {
+            line: None,
+            order: ReleaseOrder::Date,
+        }
+    }
+}
+
+/// Whether `version` belongs to a maintenance line named after the start
+/// of its versions, like `1.x` or `1.2.*`.
+pub(crate) fn line_matches(line: &str, version: &str) -> bool {
+    let prefix: Vec<&str> = line
+        .trim_end_matches(&['x', 'X', '*'][..])
+        .split('.')
+        .filter(|n| !n.is_empty())
+        .collect();
+    match Version::parse(version) {
+        Some(version) if !prefix.is_empty() && prefix.len() <= version.numbers.len() => prefix
+            .iter()
+            .zip(&version.numbers)
+            .all(|(p, n)| p.parse() == Ok(*n)),
+        _ => false,
+    }
+}
+
+/// A parsed changelog.
+///
+/// ```
+/// use changelog::{Changelog, Kind};
+///
+/// let mut changelog = Changelog::parse("# Changelog\n\n## [1.0.0] - 2020-01-01\n").unwrap();
+/// changelog.add_entry(Kind::Added, "A new feature").unwrap();
+/// changelog.release("1.1.0", "2020-02-01").unwrap();
+///
+/// let release = changelog.release_by_version("1.1.0").unwrap();
+/// assert_eq!(release.section(Kind::Added).unwrap().entries[0].text, "A new feature");
+/// assert!(changelog.to_string().contains("## [1.1.0] - 2020-02-01"));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Changelog {
+    /// Markdown before the first release: title and description.
+    pub preamble: String,
+
+    /// Releases, newest first, starting with the Unreleased one if any.
+    pub releases: Vec<Release>,
+
+    /// Link reference definitions, such as compare URLs of releases.
+    pub links: Vec<(String, String)>,
+
+    pub(crate) locale: &'static Locale,
+}
+
+impl Default for Changelog {
+    fn default() -> Self {
+        Changelog::new(Locale::english())
+    }
+}
+
+impl Changelog {
+    /// A new changelog with the standard preamble and an empty Unreleased
+    /// release.
+    pub fn new(locale: &'static Locale) -> Changelog {
+        Changelog {
+            preamble: PREAMBLE.to_string(),
+            releases: vec![Release::unreleased(locale)],
+            links: vec![],
+            locale,
+        }
+    }
+
+    /// Parses a changelog written in English.
+    pub fn parse(input: &str) -> Result<Changelog, ChangelogError> {
+        Changelog::parse_with_locale(input, Locale::english())
+    }
+
+    /// Parses a changelog whose headings are written in `locale`.
+    ///
+    /// Any input either parses or gives a `ChangelogError::Parse`, it never
+    /// panics, and a parsed changelog is written back in a form that parses
+    /// to the same document.
+    pub fn parse_with_locale(
+        input: &str,
+        locale: &'static Locale,
+    ) -> Result<Changelog, ChangelogError> {
+        parse::parse(input, locale)
+    }
+
+    /// Reads and parses a changelog.
+    pub fn from_reader<R: Read>(
+        mut reader: R,
+        locale: &'static Locale,
+    ) -> Result<Changelog, ChangelogError> {
+        let mut input = String::new();
+        reader.read_to_string(&mut input)?;
+        Changelog::parse_with_locale(&input, locale)
+    }
+
+    pub fn locale(&self) -> &'static Locale {
+        self.locale
+    }
+
+    /// The block of upcoming changes of the main line.
+    pub fn unreleased(&self) -> Option<&Release> {
+        self.unreleased_line(None)
+    }
+
+    /// The block of upcoming changes of a maintenance line, e.g. `1.x` for
+    /// `## [Unreleased 1.x]`, or of the main line for `None`.
+    pub fn unreleased_line(&self, line: Option<&str>) -> Option<&Release> {
+        self.releases
+            .iter()
+            .find(|r| r.unreleased && self.same_line(self.line(r), line))
+    }
+
+    /// Maintenance line of an Unreleased release, `None` for the main one.
+    pub fn line<'a>(&self, release: &'a Release) -> Option<&'a str> {
+        self.locale.unreleased_line(&release.version).flatten()
+    }
+
+    /// Maintenance lines of the Unreleased releases.
+    pub fn lines(&self) -> Vec<&str> {
+        self.releases
+            .iter()
+            .filter(|r| r.unreleased)
+            .filter_map(|r| self.line(r))
+            .collect()
+    }
+
+    /// Maintenance line a version belongs to: the one named after the
+    /// longest start of it, if any.
+    pub fn line_of_version(&self, version: &str) -> Option<&str> {
+        self.lines()
+            .into_iter()
+            .filter(|line| line_matches(line, version))
+            .max_by_key(|line| line.len())
+    }
+
+    pub(crate) fn same_line(&self, a: Option<&str>, b: Option<&str>) -> bool {
+        match (a, b) {
+            (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+            (a, b) => a.is_none() && b.is_none(),
+        }
+    }
+
+    /// The most recent release.
+    pub fn latest(&self) -> Option<&Release> {
+        self.releases.iter().find(|r| !r.unreleased)
+    }
+
+    pub fn release_by_version(&self, version: &str) -> Option<&Release> {
+        self.releases
+            .iter()
+            .find(|r| !r.unreleased && r.version == version)
+    }
+
+    /// Release with version `name`, or the Unreleased release of the line
+    /// it names, e.g. `Unreleased 1.x`.
+    pub fn release_by_name(&self, name: &str) -> Option<&Release> {
+        self.release_position(name)
+            .ok()
+            .map(|position| &self.releases[position])
+    }
+
+    /// Target of a link reference definition, e.g. a release's compare URL.
+    pub fn link(&self, label: &str) -> Option<&str> {
+        self.links
+            .iter()
+            .find(|(l, _)| l.eq_ignore_ascii_case(label))
+            .map(|(_, url)| url.as_str())
+    }
+
+    /// URL of a release: the link of its heading, or else the target of
+    /// the link reference definition of its version.
+    pub fn release_url<'a>(&'a self, release: &'a Release) -> Option<&'a str> {
+        match &release.style.version {
+            VersionStyle::Linked(url) => Some(url),
+            _ => self.link(&release.version),
+        }
+    }
+
+    /// Position of the Unreleased release of a line, creating it as needed:
+    /// the main one on top, the others below the Unreleased releases.
+    fn unreleased_position(&mut self, line: Option<&str>) -> usize {
+        let position = self
+            .releases
+            .iter()
+            .position(|r| r.unreleased && self.same_line(self.line(r), line));
+        match (position, line) {
+            (Some(position), _) => position,
+            (None, None) => {
+                self.releases.insert(0, Release::unreleased(self.locale));
+                0
+            }
+            (None, Some(line)) => {
+                let position = self.releases.iter().take_while(|r| r.unreleased).count();
+                let mut release = Release::unreleased(self.locale);
+                release.version = format!("{} {}", self.locale.unreleased, line);
+                self.releases.insert(position, release);
+                position
+            }
+        }
+    }
+
+    /// Appends an entry to the Unreleased release, creating it and the
+    /// section as needed.
+    pub fn add_entry(&mut self, kind: Kind, text: &str) -> Result<(), ChangelogError> {
+        self.add_line_entry(kind, text, None)
+    }
+
+    /// Appends an entry to the Unreleased release of a maintenance line, or
+    /// of the main line for `None`, creating it and the section as needed.
+    pub fn add_line_entry(
+        &mut self,
+        kind: Kind,
+        text: &str,
+        line: Option<&str>,
+    ) -> Result<(), ChangelogError> {
+        if text.trim().is_empty() {
+            return Err(ChangelogError::Validation(String::from(
+                "the entry is empty",
+            )));
+        }
+        if !parse::is_item(&Entry::new(text).text) {
+            return Err(ChangelogError::Validation(String::from(
+                "the entry does not fit in a single list item",
+            )));
+        }
+
+        let locale = self.locale;
+        let position = self.unreleased_position(line);
+        self.releases[position].push_entry(kind, Entry::new(text), locale);
+        Ok(())
+    }
+
+    /// Position of the release `version` names: one with this version, or
+    /// the Unreleased release of a line, e.g. `Unreleased 1.x`.
+    fn release_position(&self, version: &str) -> Result<usize, ChangelogError> {
+        let position = match self.locale.unreleased_line(version) {
+            Some(line) => self
+                .releases
+                .iter()
+                .position(|r| r.unreleased && self.same_line(self.line(r), line)),
+            None => self
+                .releases
+                .iter()
+                .position(|r| !r.unreleased && r.version == version),
+        };
+        position.ok_or_else(|| ChangelogError::NotFound(format!("release {} not found", version)))
+    }
+
+    /// Position of the entry with id `id` in the release at `release`: the
+    /// index of its section and its index in the section.
+    pub(crate) fn entry_position(
+        &self,
+        release: usize,
+        id: &str,
+    ) -> Result<(usize, usize), ChangelogError> {
+        let release = &self.releases[release];
+        release
+            .sections
+            .iter()
+            .enumerate()
+            .find_map(|(i, section)| {
+                section
+                    .entries
+                    .iter()
+                    .position(|entry| entry.id().eq_ignore_ascii_case(id.trim()))
+                    .map(|j| (i, j))
+            })
+            .ok_or_else(|| {
+                ChangelogError::NotFound(format!("there is no entry {} in {}", id, release.version))
+            })
+    }
+
+    /// Entry with id `id` in release `version`.
+    pub fn entry(&self, version: &str, id: &str) -> Result<&Entry, ChangelogError> {
+        let position = self.release_position(version)?;
+        let (i, j) = self.entry_position(position, id)?;
+        Ok(&self.releases[position].sections[i].entries[j])
+    }
+
+    /// Replaces the description and sections of release `version` with the
+    /// ones `body` has, as `Release::body` writes them.
+    ///
+    /// Errors are on the lines of `body`, which must not start another
+    /// release. The link reference definitions it has are added, replacing
+    /// those with the same labels.
+    pub fn set_release_body(&mut self, version: &str, body: &str) -> Result<(), ChangelogError> {
+        let position = self.release_position(version)?;
+        let input = format!("## {}\n\n{}", self.releases[position].heading(), body);
+        let parsed = parse::parse(&input, self.locale).map_err(|err| match err {
+            ChangelogError::Parse { line, message } => ChangelogError::Parse {
+                line: line.saturating_sub(2).max(1),
+                message,
+            },
+            err => err,
+        })?;
+        if let Some(other) = parsed.releases.get(1) {
+            return Err(ChangelogError::Validation(format!(
+                "the changes of {} cannot have another release, {}",
+                self.releases[position].version, other.version
+            )));
+        }
+
+        for (label, url) in parsed.links {
+            match self
+                .links
+                .iter_mut()
+                .find(|(l, _)| l.eq_ignore_ascii_case(&label))
+            {
+                Some(link) => link.1 = url,
+                None => self.links.push((label, url)),
+            }
+        }
+        let release = &mut self.releases[position];
+        match parsed.releases.into_iter().next() {
+            Some(edited) => {
+                release.description = edited.description;
+                release.sections = edited.sections;
+            }
+            None => {
+                release.description.clear();
+                release.sections.clear();
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the entry with id `id` from release `version`, and its
+    /// section if nothing is left in it.
+    pub fn remove_entry(&mut self, version: &str, id: &str) -> Result<Entry, ChangelogError> {
+        let position = self.release_position(version)?;
+        let (i, j) = self.entry_position(position, id)?;
+        let sections = &mut self.releases[position].sections;
+        let entry = sections[i].entries.remove(j);
+        if sections[i].entries.is_empty() && sections[i].description.is_empty() {
+            sections.remove(i);
+        }
+        Ok(entry)
+    }
+
+    /// Replaces the text of the entry with id `id` in release `version`,
+    /// which gives it a new id.
+    pub fn edit_entry(
+        &mut self,
+        version: &str,
+        id: &str,
+        text: &str,
+    ) -> Result<(), ChangelogError> {
+        let position = self.release_position(version)?;
+        let (i, j) = self.entry_position(position, id)?;
+        let entry = Entry::new(text);
+        if entry.text.is_empty() {
+            return Err(ChangelogError::Validation(String::from(
+                "the entry is empty",
+            )));
+        }
+        if !parse::is_item(&entry.text) {
+            return Err(ChangelogError::Validation(String::from(
+                "the entry does not fit in a single list item",
+            )));
+        }
+        let changed = self.releases[position].sections[i].entries[j] != entry;
+        if changed && self.entry_position(position, &entry.id()).is_ok() {
+            return Err(ChangelogError::Conflict(format!(
+                "entry {} is already in {}",
+                entry.id(),
+                self.releases[position].version
+            )));
+        }
+        self.releases[position].sections[i].entries[j] = entry;
+        Ok(())
+    }
+
+    /// Moves the entry with id `id` in release `version` to the section of
+    /// kind `kind`, at its end.
+    pub fn move_entry(
+        &mut self,
+        version: &str,
+        id: &str,
+        kind: Kind,
+    ) -> Result<(), ChangelogError> {
+        let position = self.release_position(version)?;
+        let (i, _) = self.entry_position(position, id)?;
+        if self.releases[position].sections[i].kind == Some(kind) {
+            return Ok(());
+        }
+        let entry = self.remove_entry(version, id)?;
+        let locale = self.locale;
+        self.releases[position].push_entry(kind, entry, locale);
+        Ok(())
+    }
+
+    /// Turns the Unreleased changes into release `version`, leaving an
+    /// empty Unreleased release on top.
+    ///
+    /// Compare links following the `.../compare/<tag>...HEAD` convention
+    /// are updated too.
+    pub fn release(&mut self, version: &str, date: &str) -> Result<(), ChangelogError> {
+        self.release_with(version, date, &ReleaseOptions::default())
+    }
+
+    /// Turns the Unreleased changes of a maintenance line into release
+    /// `version`, leaving its Unreleased release empty, and puts the
+    /// release where `options.order` says.
+    pub fn release_with(
+        &mut self,
+        version: &str,
+        date: &str,
+        options: &ReleaseOptions,
+    ) -> Result<(), ChangelogError> {
+        lazy_static! {
+            static ref VERSION_PATTERN: Regex = Regex::new(r"^[^\s\[\]]+$").unwrap();
+        }
+
+        if !VERSION_PATTERN.is_match(version) || self.locale.is_unreleased(version) {
+            return Err(ChangelogError::Validation(format!(
+                "'{}' is not a version",
+                version
+            )));
+        }
+        date.parse::<Date>()?;
+        if self.release_by_version(version).is_some() {
+            return Err(ChangelogError::Conflict(format!(
+                "release {} already exists",
+                version
+            )));
+        }
+        let line = match &options.line {
+            Some(line) => Some(line.to_string()),
+            None => self.line_of_version(version).map(String::from),
+        };
+        let position = self
+            .unreleased_line(line.as_deref())
+            .and_then(|unreleased| {
+                self.releases
+                    .iter()
+                    .position(|r| std::ptr::eq(r, unreleased))
+            })
+            .ok_or_else(|| {
+                ChangelogError::NotFound(match &line {
+                    Some(line) => {
+                        format!("there is no {} {} release", self.locale.unreleased, line)
+                    }
+                    None => String::from("there is no Unreleased release"),
+                })
+            })?;
+
+        // the release is written like the previous one of its line, and the
+        // new Unreleased heading like the old one
+        let mut style = self
+            .releases
+            .iter()
+            .filter(|r| !r.unreleased)
+            .find(|r| self.same_line(self.line_of_version(&r.version), line.as_deref()))
+            .map_or_else(HeadingStyle::default, |r| r.style.clone());
+        if let VersionStyle::Linked(_) = style.version {
+            style.version = VersionStyle::Bracketed;
+        }
+        let mut release = self.releases.remove(position);
+        let mut unreleased = Release::unreleased(self.locale);
+        if let Some(line) = &line {
+            unreleased.version = format!("{} {}", self.locale.unreleased, line);
+        }
+        unreleased.style = std::mem::replace(&mut release.style, style);
+        let unreleased_label = std::mem::replace(&mut release.version, version.to_string());
+        release.date = Some(date.to_string());
+        release.unreleased = false;
+        self.releases.insert(position, unreleased);
+
+        let top = self.releases.iter().take_while(|r| r.unreleased).count();
+        let index = match (options.order, Version::parse(version)) {
+            (ReleaseOrder::Version, Some(new)) => self.releases[top..]
+                .iter()
+                .position(|r| Version::parse(&r.version).is_some_and(|v| v < new))
+                .map_or(self.releases.len(), |i| top + i),
+            _ => top,
+        };
+        self.releases.insert(index, release);
+        let position = if index <= position {
+            position + 1
+        } else {
+            position
+        };
+
+        self.update_links(position, index, &unreleased_label, version);
+        Ok(())
+    }
+
+    fn update_links(
+        &mut self,
+        unreleased: usize,
+        release: usize,
+        unreleased_label: &str,
+        version: &str,
+    ) {
+        // an inline link of the Unreleased heading is updated the same way,
+        // and the release gets one too
+        if let VersionStyle::Linked(url) = &self.releases[unreleased].style.version {
+            if let Some((unreleased_url, release_url)) = compare_urls(url, version) {
+                self.releases[unreleased].style.version = VersionStyle::Linked(unreleased_url);
+                self.releases[release].style.version = VersionStyle::Linked(release_url);
+            }
+        }
+
+        let position = match self
+            .links
+            .iter()
+            .position(|(l, _)| l.eq_ignore_ascii_case(unreleased_label))
+        {
+            Some(position) => position,
+            None => return,
+        };
+        let (unreleased_url, release_url) = match compare_urls(&self.links[position].1, version) {
+            Some(urls) => urls,
+            None => return,
+        };
+        self.links[position] = (self.releases[unreleased].version.clone(), unreleased_url);
+        self.links
+            .insert(position + 1, (version.to_string(), release_url));
+    }
+
+    /// Marks a release as yanked.
+    pub fn yank(&mut self, version: &str) -> Result<(), ChangelogError> {
+        match self
+            .releases
+            .iter_mut()
+            .find(|r| !r.unreleased && r.version == version)
+        {
+            Some(release) => {
+                release.yanked = true;
+                Ok(())
+            }
+            None => Err(ChangelogError::NotFound(format!("release {}", version))),
+        }
+    }
+
+    /// Events of the whole document, except link reference definitions.
+    ///
+    /// They can be rendered by any pulldown-cmark backend, e.g.
+    /// `pulldown_cmark::html::push_html`.
+    pub fn events(&self) -> Vec<Event<'_>> {
+        self.document_events(|markdown, item| self.markdown_events(markdown, item))
+    }
+
+    /// Events of the body of `release`: its description and sections.
+    pub fn release_events<'a>(&'a self, release: &'a Release) -> Vec<Event<'a>> {
+        let mut events = vec![];
+        release_events(
+            release,
+            &|markdown, item| self.markdown_events(markdown, item),
+            &mut events,
+        );
+        events
+    }
+
+    /// The body of `release` as HTML.
+    pub fn release_html(&self, release: &Release) -> String {
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(&mut html, self.release_events(release).into_iter());
+        html
+    }
+
+    /// Some Markdown of the changelog as HTML, told whether it is a list
+    /// item as for `document_events`.
+    pub(crate) fn html(&self, markdown: &str, item: bool) -> String {
+        let mut html = String::new();
+        pulldown_cmark::html::push_html(
+            &mut html,
+            self.markdown_events(markdown, item).into_iter(),
+        );
+        html
+    }
+
+    /// Events of a part of the document, with reference links resolved
+    /// against the changelog's link definitions.
+    pub(crate) fn markdown_events<'a>(&'a self, markdown: &'a str, item: bool) -> Vec<Event<'a>> {
+        let mut resolve = |link: BrokenLink| {
+            self.link(&link.reference)
+                .map(|url| (CowStr::Borrowed(url), CowStr::Borrowed("")))
+        };
+        let events: Vec<Event> =
+            Parser::new_with_broken_link_callback(markdown, Options::empty(), Some(&mut resolve))
+                .collect();
+        if item {
+            item_events(events)
+        } else {
+            events
+        }
+    }
+
+    /// Events of the document's structure, with the Markdown of each part
+    /// produced by `markdown`, which is told whether it is a list item.
+    fn document_events<'a, F>(&'a self, markdown: F) -> Vec<Event<'a>>
+    where
+        F: Fn(&'a str, bool) -> Vec<Event<'a>>,
+    {
+        let mut events = markdown(&self.preamble, false);
+
+        for release in &self.releases {
+            events.push(Event::Start(Tag::Heading(HEADING_RELEASE, None, vec![])));
+            events.push(Event::Text(CowStr::Boxed(
+                release.heading().into_boxed_str(),
+            )));
+            events.push(Event::End(Tag::Heading(HEADING_RELEASE, None, vec![])));
+            release_events(release, &markdown, &mut events);
+        }
+        events
+    }
+
+    /// Writes the changelog as Markdown.
+    ///
+    /// The Markdown of descriptions and entries is written as it was read,
+    /// so that writing and parsing again gives back the same document.
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), ChangelogError> {
+        let mut events = self.document_events(raw_events);
+
+        let mut links: String = self
+            .links
+            .iter()
+            .map(|(label, url)| format!("[{}]: {}\n", label, url))
+            .collect();
+        if !links.is_empty() {
+            // a blank line keeps definitions out of Markdown written just
+            // before them, which they would otherwise continue
+            if let Some(Event::Html(_)) = events.last() {
+                links.insert(0, '\n');
+            }
+            events.push(Event::Html(CowStr::Boxed(links.into_boxed_str())));
+        }
+
+        markdown::write(&mut writer, events.into_iter())?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Changelog {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut buffer = Vec::new();
+        self.write(&mut buffer).map_err(|_| fmt::Error)?;
+        f.write_str(&String::from_utf8_lossy(&buffer))
+    }
+}
+
+/// Events writing Markdown as it is, for `Changelog::write`.
+fn raw_events(markdown: &str, item: bool) -> Vec<Event<'_>> {
+    match markdown {
+        "" => vec![],
+        _ if item => vec![Event::Html(CowStr::Borrowed(markdown))],
+        _ => vec![Event::Html(CowStr::Boxed(
+            format!("{}\n", markdown).into_boxed_str(),
+        ))],
+    }
+}
+
+/// Events of a release's description and sections, with the Markdown of
+/// each part produced by `markdown` as in `Changelog::document_events`.
+fn release_events<'a, F>(release: &'a Release, markdown: &F, events: &mut Vec<Event<'a>>)
+where
+    F: Fn(&'a str, bool) -> Vec<Event<'a>>,
+{
+    events.extend(markdown(&release.description, false));
+
+    for section in &release.sections {
+        events.push(Event::Start(Tag::Heading(HEADING_SECTION, None, vec![])));
+        events.push(Event::Text(CowStr::Borrowed(&section.title)));
+        events.push(Event::End(Tag::Heading(HEADING_SECTION, None, vec![])));
+        events.extend(markdown(&section.description, false));
+
+        if !section.entries.is_empty() {
+            events.push(Event::Start(Tag::List(None)));
+            for entry in &section.entries {
+                events.push(Event::Start(Tag::Item));
+                events.extend(markdown(&entry.text, true));
+                events.push(Event::End(Tag::Item));
+            }
+            events.push(Event::End(Tag::List(None)));
+        }
+    }
+}
+
+/// Events of a list item's Markdown.
+///
+/// The paragraph wrapping a single-paragraph item is dropped, so that it is
+/// written as an item of a tight list.
+fn item_events(events: Vec<Event<'_>>) -> Vec<Event<'_>> {
+    let mut depth = 0;
+    let mut paragraphs = 0;
+    for event in &events {
+        match event {
+            Event::Start(tag) => {
+                if depth == 0 && *tag == Tag::Paragraph {
+                    paragraphs += 1;
+                }
+                depth += 1;
+            }
+            Event::End(_) => depth -= 1,
+            _ => (),
+        }
+    }
+    if paragraphs != 1 {
+        return events;
+    }
+
+    depth = 0;
+    events
+        .into_iter()
+        .filter(|event| match event {
+            Event::Start(tag) => {
+                depth += 1;
+                !(depth == 1 && *tag == Tag::Paragraph)
+            }
+            Event::End(tag) => {
+                depth -= 1;
+                !(depth == 0 && *tag == Tag::Paragraph)
+            }
+            _ => true,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINES: &str = "# Changelog
+
+## [Unreleased]
+
+### Added
+
+- Feature C
+
+## [Unreleased 1.x]
+
+### Fixed
+
+- Crash on start
+
+## [2.1.0] - 2020-06-01
+
+## [2.0.0] - 2020-05-01
+
+## [1.4.2] - 2020-04-01
+
+[Unreleased]: https://github.com/o/r/compare/v2.1.0...HEAD
+[Unreleased 1.x]: https://github.com/o/r/compare/v1.4.2...1.x
+[2.1.0]: https://github.com/o/r/compare/v2.0.0...v2.1.0
+[2.0.0]: https://github.com/o/r/compare/v1.4.2...v2.0.0
+[1.4.2]: https://github.com/o/r/compare/v1.4.1...v1.4.2
+";
+
+    fn released(version: &str, options: &ReleaseOptions) -> Changelog {
+        let mut changelog = Changelog::parse(LINES).unwrap();
+        changelog
+            .release_with(version, "2020-07-01", options)
+            .unwrap();
+        changelog
+    }
+
+    fn versions(changelog: &Changelog) -> Vec<&str> {
+        changelog
+            .releases
+            .iter()
+            .map(|r| r.version.as_str())
+            .collect()
+    }
+
+    fn entries(release: &Release) -> Vec<&str> {
+        release
+            .sections
+            .iter()
+            .flat_map(|s| &s.entries)
+            .map(|e| e.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn lines_by_version() {
+        let changelog = Changelog::parse(LINES).unwrap();
+        assert_eq!(changelog.lines(), ["1.x"]);
+        assert_eq!(changelog.line_of_version("1.4.3"), Some("1.x"));
+        assert_eq!(changelog.line_of_version("2.2.0"), None);
+        assert!(line_matches("1.2.*", "1.2.3"));
+        assert!(!line_matches("1.2.*", "1.20.0"));
+        assert!(!line_matches("x", "1.0.0"));
+    }
+
+    #[test]
+    fn longest_line_of_version() {
+        let mut changelog = Changelog::parse(LINES).unwrap();
+        changelog
+            .add_line_entry(Kind::Fixed, "Leak", Some("1.4.x"))
+            .unwrap();
+        assert_eq!(changelog.line_of_version("1.4.3"), Some("1.4.x"));
+        assert_eq!(changelog.line_of_version("1.3.0"), Some("1.x"));
+    }
+
+    #[test]
+    fn line_entries_go_below_the_main_unreleased() {
+        let mut changelog = Changelog::parse(LINES).unwrap();
+        changelog
+            .add_line_entry(Kind::Fixed, "Leak", Some("2.1.x"))
+            .unwrap();
+        changelog
+            .add_line_entry(Kind::Fixed, "Typo", Some("1.X"))
+            .unwrap();
+        assert_eq!(
+            versions(&changelog)[..3],
+            ["Unreleased", "Unreleased 1.x", "Unreleased 2.1.x"]
+        );
+        assert_eq!(entries(&changelog.releases[1]), ["Crash on start", "Typo"]);
+        assert_eq!(entries(&changelog.releases[2]), ["Leak"]);
+    }
+
+    #[test]
+    fn release_of_a_line_by_date() {
+        let changelog = released("1.4.3", &ReleaseOptions::default());
+        assert_eq!(
+            versions(&changelog),
+            [
+                "Unreleased",
+                "Unreleased 1.x",
+                "1.4.3",
+                "2.1.0",
+                "2.0.0",
+                "1.4.2"
+            ]
+        );
+        assert_eq!(entries(&changelog.releases[0]), ["Feature C"]);
+        assert!(changelog.releases[1].is_empty());
+        assert_eq!(entries(&changelog.releases[2]), ["Crash on start"]);
+        assert_eq!(
+            changelog.link("Unreleased 1.x"),
+            Some("https://github.com/o/r/compare/v1.4.3...1.x")
+        );
+        assert_eq!(
+            changelog.link("1.4.3"),
+            Some("https://github.com/o/r/compare/v1.4.2...v1.4.3")
+        );
+        assert_eq!(
+            changelog.link("Unreleased"),
+            Some("https://github.com/o/r/compare/v2.1.0...HEAD")
+        );
+    }
+
+    #[test]
+    fn release_of_a_line_by_version() {
+        let options = ReleaseOptions {
+            line: None,
+            order: ReleaseOrder::Version,
+        };
+        let changelog = released("1.4.3", &options);
+        assert_eq!(
+            versions(&changelog),
+            [
+                "Unreleased",
+                "Unreleased 1.x",
+                "2.1.0",
+                "2.0.0",
+                "1.4.3",
+                "1.4.2"
+            ]
+        );
+        assert_eq!(entries(&changelog.releases[4]), ["Crash on start"]);
+        assert_eq!(
+            changelog.link("1.4.3"),
+            Some("https://github.com/o/r/compare/v1.4.2...v1.4.3")
+        );
+        assert_eq!(Changelog::parse(&changelog.to_string()).unwrap(), changelog);
+    }
+
+    #[test]
+    fn release_of_the_main_line() {
+        for order in ReleaseOrder::ALL.iter().copied() {
+            let options = ReleaseOptions { line: None, order };
+            let changelog = released("2.2.0", &options);
+            assert_eq!(
+                versions(&changelog),
+                [
+                    "Unreleased",
+                    "Unreleased 1.x",
+                    "2.2.0",
+                    "2.1.0",
+                    "2.0.0",
+                    "1.4.2"
+                ],
+                "{:?}",
+                order
+            );
+            assert_eq!(entries(&changelog.releases[2]), ["Feature C"]);
+            assert_eq!(entries(&changelog.releases[1]), ["Crash on start"]);
+            assert_eq!(
+                changelog.link("Unreleased"),
+                Some("https://github.com/o/r/compare/v2.2.0...HEAD")
+            );
+            assert_eq!(
+                changelog.link("Unreleased 1.x"),
+                Some("https://github.com/o/r/compare/v1.4.2...1.x")
+            );
+        }
+    }
+
+    #[test]
+    fn release_of_an_explicit_line() {
+        // a version matching no line is released from the one given
+        let options = ReleaseOptions {
+            line: Some(String::from("1.x")),
+            order: ReleaseOrder::Version,
+        };
+        let changelog = released("1.5.0-rc.1", &options);
+        assert_eq!(
+            entries(changelog.release_by_version("1.5.0-rc.1").unwrap()),
+            ["Crash on start"]
+        );
+        assert_eq!(
+            versions(&changelog),
+            [
+                "Unreleased",
+                "Unreleased 1.x",
+                "2.1.0",
+                "2.0.0",
+                "1.5.0-rc.1",
+                "1.4.2"
+            ]
+        );
+
+        let mut changelog = Changelog::parse(LINES).unwrap();
+        let options = ReleaseOptions {
+            line: Some(String::from("5.x")),
+            order: ReleaseOrder::Date,
+        };
+        match changelog.release_with("5.0.1", "2020-07-01", &options) {
+            Err(ChangelogError::NotFound(message)) => {
+                assert_eq!(message, "there is no Unreleased 5.x release")
+            }
+            result => panic!("{:?}", result),
+        }
+        assert_eq!(changelog, Changelog::parse(LINES).unwrap());
+    }
+
+    #[test]
+    fn release_urls() {
+        let input = "# Changelog
+
+## [Unreleased]
+
+## [1.1.0](https://github.com/o/r/compare/v1.0.0...v1.1.0) - 2020-02-01
+
+## [1.0.0] - 2020-01-01
+
+## 0.1.0 - 2019-01-01
+
+[1.0.0]: https://github.com/o/r/releases/tag/v1.0.0
+[unreleased]: https://github.com/o/r/compare/v1.1.0...HEAD
+";
+        let changelog = Changelog::parse(input).unwrap();
+        let urls: Vec<Option<&str>> = changelog
+            .releases
+            .iter()
+            .map(|r| changelog.release_url(r))
+            .collect();
+        assert_eq!(
+            urls,
+            [
+                Some("https://github.com/o/r/compare/v1.1.0...HEAD"),
+                Some("https://github.com/o/r/compare/v1.0.0...v1.1.0"),
+                Some("https://github.com/o/r/releases/tag/v1.0.0"),
+                None
+            ]
+        );
+
+        let (_, markdown) = crate::notes::TEMPLATES[0];
+        let notes = changelog
+            .release_notes(&changelog.releases[1], "r", markdown)
+            .unwrap();
+        assert!(
+            notes.contains("Changes since 1.0.0: https://github.com/o/r/compare/v1.0.0...v1.1.0")
+        );
+    }
+
+    const ENTRIES: &str = "# Changelog
+
+## [Unreleased]
+
+### Added
+
+- Feature
+- Same text
+
+### Fixed
+
+- Same text
+- Crash
+
+## [1.0.0] - 2020-01-01
+
+### Added
+
+- Initial
+";
+
+    fn id(text: &str) -> String {
+        Entry::new(text).id()
+    }
+
+    fn sections(release: &Release) -> Vec<(&str, Vec<&str>)> {
+        release
+            .sections
+            .iter()
+            .map(|s| {
+                let entries = s.entries.iter().map(|e| e.text.as_str()).collect();
+                (s.title.as_str(), entries)
+            })
+            .collect()
+    }
+
+    fn not_found<T: fmt::Debug>(result: Result<T, ChangelogError>) {
+        match result {
+            Err(err @ ChangelogError::NotFound(_)) => assert_eq!(err.exit_code(), 6),
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn entries_by_id() {
+        let changelog = Changelog::parse(ENTRIES).unwrap();
+        assert_eq!(id("Crash"), Entry::new(" Crash\n").id());
+        assert_eq!(
+            changelog.entry("Unreleased", &id("Crash")).unwrap().text,
+            "Crash"
+        );
+        assert_eq!(
+            changelog
+                .entry("1.0.0", &id("Initial").to_uppercase())
+                .unwrap()
+                .text,
+            "Initial"
+        );
+        let (release, kind, entry) = changelog.find_entry(&id("Initial").to_uppercase()).unwrap();
+        assert_eq!(
+            (release.version.as_str(), kind, entry.text.as_str()),
+            ("1.0.0", Kind::Added, "Initial")
+        );
+
+        not_found(changelog.entry("Unreleased", &id("Initial")));
+        not_found(changelog.entry("2.0.0", &id("Initial")));
+        not_found(changelog.entry("Unreleased 1.x", &id("Crash")));
+        not_found(changelog.find_entry("0000000"));
+    }
+
+    #[test]
+    fn remove_entries() {
+        let mut changelog = Changelog::parse(ENTRIES).unwrap();
+        // the same text has the same id, which finds the first entry
+        let removed = changelog
+            .remove_entry("Unreleased", &id("Same text"))
+            .unwrap();
+        assert_eq!(removed.text, "Same text");
+        assert_eq!(
+            sections(&changelog.releases[0]),
+            [
+                ("Added", vec!["Feature"]),
+                ("Fixed", vec!["Same text", "Crash"])
+            ]
+        );
+
+        changelog
+            .remove_entry("Unreleased", &id("Feature"))
+            .unwrap();
+        assert_eq!(
+            sections(&changelog.releases[0]),
+            [("Fixed", vec!["Same text", "Crash"])]
+        );
+        not_found(changelog.remove_entry("Unreleased", &id("Feature")));
+        not_found(changelog.remove_entry("Unreleased", "not an id"));
+    }
+
+    #[test]
+    fn edit_entries() {
+        let mut changelog = Changelog::parse(ENTRIES).unwrap();
+        changelog
+            .edit_entry("Unreleased", &id("Crash"), "Crash on start")
+            .unwrap();
+        assert_eq!(
+            sections(&changelog.releases[0])[1],
+            ("Fixed", vec!["Same text", "Crash on start"])
+        );
+        not_found(changelog.entry("Unreleased", &id("Crash")));
+
+        // editing a repeated entry changes the first one
+        changelog
+            .edit_entry("Unreleased", &id("Same text"), "Other text")
+            .unwrap();
+        assert_eq!(
+            sections(&changelog.releases[0])[0],
+            ("Added", vec!["Feature", "Other text"])
+        );
+
+        match changelog.edit_entry("Unreleased", &id("Feature"), "Crash on start") {
+            Err(ChangelogError::Conflict(_)) => (),
+            result => panic!("{:?}", result),
+        }
+        match changelog.edit_entry("Unreleased", &id("Feature"), " \n") {
+            Err(ChangelogError::Validation(_)) => (),
+            result => panic!("{:?}", result),
+        }
+        changelog
+            .edit_entry("Unreleased", &id("Feature"), "Feature")
+            .unwrap();
+        not_found(changelog.edit_entry("1.0.0", &id("Feature"), "Feature"));
+    }
+
+    #[test]
+    fn move_entries() {
+        let mut changelog = Changelog::parse(ENTRIES).unwrap();
+        changelog
+            .move_entry("Unreleased", &id("Same text"), Kind::Fixed)
+            .unwrap();
+        assert_eq!(
+            sections(&changelog.releases[0]),
+            [
+                ("Added", vec!["Feature"]),
+                ("Fixed", vec!["Same text", "Crash", "Same text"])
+            ]
+        );
+
+        changelog
+            .move_entry("Unreleased", &id("Feature"), Kind::Changed)
+            .unwrap();
+        assert_eq!(
+            sections(&changelog.releases[0]),
+            [
+                ("Changed", vec!["Feature"]),
+                ("Fixed", vec!["Same text", "Crash", "Same text"])
+            ]
+        );
+        changelog
+            .move_entry("Unreleased", &id("Feature"), Kind::Changed)
+            .unwrap();
+        assert_eq!(
+            sections(&changelog.releases[0])[0],
+            ("Changed", vec!["Feature"])
+        );
+        not_found(changelog.move_entry("1.0.0", &id("Feature"), Kind::Added));
+    }
+
+    #[test]
+    fn set_release_bodies() {
+        let mut changelog = Changelog::parse(ENTRIES).unwrap();
+        changelog
+            .set_release_body(
+                "Unreleased",
+                "Intro, see [docs].\n\n### Fixed\n\n- Crash\n\n[docs]: https://docs\n[1.0.0]: https://one\n",
+            )
+            .unwrap();
+        let release = &changelog.releases[0];
+        assert_eq!(release.description, "Intro, see [docs].");
+        assert_eq!(sections(release), [("Fixed", vec!["Crash"])]);
+        assert_eq!(changelog.link("docs"), Some("https://docs"));
+
+        // a definition typed again replaces the one there was
+        changelog
+            .set_release_body("1.0.0", "[1.0.0]: https://two\n")
+            .unwrap();
+        assert_eq!(changelog.link("1.0.0"), Some("https://two"));
+        assert!(changelog.releases[1].sections.is_empty());
+        assert_eq!(changelog.links.len(), 2);
+        assert_eq!(Changelog::parse(&changelog.to_string()).unwrap(), changelog);
+
+        changelog.set_release_body("Unreleased", "").unwrap();
+        assert!(changelog.releases[0].is_empty());
+        assert_eq!(changelog.releases[0].description, "");
+
+        match changelog.set_release_body("Unreleased", "- a\n\n## [2.0.0]\n") {
+            Err(ChangelogError::Validation(message)) => assert_eq!(
+                message,
+                "the changes of Unreleased cannot have another release, 2.0.0"
+            ),
+            result => panic!("{:?}", result),
+        }
+        match changelog.set_release_body("Unreleased", "```\n") {
+            Err(ChangelogError::Parse { line: 1, .. }) => (),
+            result => panic!("{:?}", result),
+        }
+        not_found(changelog.set_release_body("2.0.0", ""));
+    }
+}
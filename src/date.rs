@@ -0,0 +1,144 @@
+//! Calendar dates of releases, as written in their headings.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::ChangelogError;
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// A date of the proleptic Gregorian calendar, written `YYYY-MM-DD`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+}
+
+impl Date {
+    /// Today's date, in UTC.
+    pub fn today() -> Date {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        Date::from_days((secs / 86400) as i64)
+    }
+
+    /// Date a number of days after 1970-01-01, after Howard Hinnant's
+    /// `civil_from_days`.
+    fn from_days(days: i64) -> Date {
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Date {
+            year,
+            month: month as u32,
+            day: day as u32,
+        }
+    }
+
+    /// Number of days since 1970-01-01, the inverse of `from_days`.
+    fn days(self) -> i64 {
+        let year = if self.month <= 2 {
+            self.year - 1
+        } else {
+            self.year
+        };
+        let era = year.div_euclid(400);
+        let yoe = year - era * 400;
+        let month = i64::from(self.month);
+        let mp = if month > 2 { month - 3 } else { month + 9 };
+        let doy = (153 * mp + 2) / 5 + i64::from(self.day) - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    /// Abbreviated English name of the day of the week, e.g. `Mon`.
+    pub fn weekday_name(self) -> &'static str {
+        // 1970-01-01 was a Thursday
+        WEEKDAYS[(self.days() + 3).rem_euclid(7) as usize]
+    }
+
+    /// Abbreviated English name of the month, e.g. `Jan`.
+    pub fn month_name(self) -> &'static str {
+        MONTHS[self.month as usize - 1]
+    }
+
+    /// Midnight UTC of the date, as in RFC 2822: `Tue, 20 Jun 2017 00:00:00 +0000`.
+    pub fn rfc2822(self) -> String {
+        format!(
+            "{}, {:02} {} {:04} 00:00:00 +0000",
+            self.weekday_name(),
+            self.day,
+            self.month_name(),
+            self.year
+        )
+    }
+
+    /// Date of an RFC 2822 date and time, e.g. `Tue, 20 Jun 2017 10:00:00 +0200`,
+    /// in its own time zone.
+    pub fn from_rfc2822(s: &str) -> Option<Date> {
+        let s = s.rsplit(", ").next()?;
+        let mut parts = s.split_whitespace();
+        let day: u32 = parts.next()?.parse().ok()?;
+        let month = parts.next()?;
+        let month = MONTHS.iter().position(|m| m.eq_ignore_ascii_case(month))? + 1;
+        let year: i64 = parts.next()?.parse().ok()?;
+        format!("{:04}-{:02}-{:02}", year, month, day).parse().ok()
+    }
+
+    /// Midnight UTC of the date, as in RFC 3339: `2017-06-20T00:00:00Z`.
+    pub fn rfc3339(self) -> String {
+        format!("{}T00:00:00Z", self)
+    }
+}
+
+impl FromStr for Date {
+    type Err = ChangelogError;
+
+    fn from_str(s: &str) -> Result<Date, ChangelogError> {
+        let invalid = || ChangelogError::Validation(format!("'{}' is not a YYYY-MM-DD date", s));
+
+        let parts: Vec<&str> = s.split('-').collect();
+        let date = match parts[..] {
+            [year, month, day]
+                if year.len() == 4
+                    && month.len() == 2
+                    && day.len() == 2
+                    && s.bytes().all(|b| b == b'-' || b.is_ascii_digit()) =>
+            {
+                Date {
+                    year: year.parse().map_err(|_| invalid())?,
+                    month: month.parse().map_err(|_| invalid())?,
+                    day: day.parse().map_err(|_| invalid())?,
+                }
+            }
+            _ => return Err(invalid()),
+        };
+
+        // a day past the end of its month comes back as one of the next
+        if date.month < 1 || date.month > 12 || date.day < 1 || Date::from_days(date.days()) != date
+        {
+            return Err(invalid());
+        }
+        Ok(date)
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
@@ -0,0 +1,432 @@
+//! Conversion to and from the `debian/changelog` format of Debian packages.
+//!
+//! Each release is a stanza, newest first:
+//!
+//! ```text
+//! foo (1.0.0) unstable; urgency=medium
+//!
+//!   * New visual identity by @tylerfortune8.
+//!   * Version navigation.
+//!
+//!  -- Jane Doe <jane@example.com>  Tue, 20 Jun 2017 00:00:00 +0000
+//! ```
+//!
+//! Entries are written as plain text and wrapped at 80 columns; the
+//! sections of a release are not kept, so imported entries all go to
+//! Changed. Prereleases are written as `2.0.0~rc.1`, which Debian orders
+//! before `2.0.0`, and read back as `2.0.0-rc.1`.
+
+use regex::Regex;
+
+use crate::changelog::{Changelog, Entry, HeadingStyle, Kind, Release, Section};
+use crate::date::Date;
+use crate::error::ChangelogError;
+use crate::locale::Locale;
+use crate::parse;
+use crate::schema::plain_text;
+
+const WIDTH: usize = 80;
+
+/// What a Debian changelog says that a Keep a Changelog file does not.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DebianOptions {
+    /// Name of the source package.
+    pub package: String,
+
+    /// Distribution the releases were uploaded to, e.g. `unstable`.
+    pub distribution: String,
+
+    /// Urgency of the uploads, e.g. `medium`.
+    pub urgency: String,
+
+    /// Maintainer, as `Full Name <email@example.com>`.
+    pub maintainer: String,
+}
+
+impl DebianOptions {
+    fn validate(&self) -> Result<(), ChangelogError> {
+        lazy_static! {
+            static ref PACKAGE: Regex = Regex::new(r"^[a-z0-9][a-z0-9+.-]+$").unwrap();
+            static ref WORD: Regex = Regex::new(r"^[-+0-9a-zA-Z.]+$").unwrap();
+            static ref MAINTAINER: Regex = Regex::new(r"^[^<>]*\S <[^<>\s]+@[^<>\s]+>$").unwrap();
+        }
+
+        if !PACKAGE.is_match(&self.package) {
+            return Err(ChangelogError::Validation(format!(
+                "'{}' is not a Debian package name",
+                self.package
+            )));
+        }
+        if !WORD.is_match(&self.distribution) || !WORD.is_match(&self.urgency) {
+            return Err(ChangelogError::Validation(format!(
+                "'{}' and '{}' must be single words",
+                self.distribution, self.urgency
+            )));
+        }
+        if !MAINTAINER.is_match(&self.maintainer) {
+            return Err(ChangelogError::Validation(format!(
+                "maintainer '{}' is not 'Full Name <email>'",
+                self.maintainer
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Debian version of a release: versions must start with a digit, so a
+/// tag-like `v` is dropped, and a prerelease follows a `~` rather than a
+/// `-`, so that `2.0.0~rc.1` comes before `2.0.0`.
+fn debian_version(version: &str) -> Result<String, ChangelogError> {
+    lazy_static! {
+        static ref VERSION: Regex =
+            Regex::new(r"^v?(?P<version>[0-9][A-Za-z0-9.+~:]*)(?:-(?P<pre>[A-Za-z0-9.+~:-]+))?$")
+                .unwrap();
+    }
+
+    let captures = VERSION.captures(version).ok_or_else(|| {
+        ChangelogError::Validation(format!("'{}' is not a Debian version", version))
+    })?;
+    Ok(match captures.name("pre") {
+        Some(pre) => format!("{}~{}", &captures["version"], pre.as_str()),
+        None => captures["version"].to_string(),
+    })
+}
+
+/// Appends `text` as a change, wrapping it into lines starting with
+/// `first` then `rest`, each followed by a space.
+pub(crate) fn push_change(out: &mut String, text: &str, first: &str, rest: &str) {
+    let mut line = String::from(first);
+    for word in text.split_whitespace() {
+        if line.len() > first.len().max(rest.len())
+            && line.chars().count() + 1 + word.chars().count() > WIDTH
+        {
+            out.push_str(&line);
+            out.push('\n');
+            line = String::from(rest);
+        }
+        line.push(' ');
+        line.push_str(word);
+    }
+    out.push_str(&line);
+    out.push('\n');
+}
+
+impl Changelog {
+    /// The releases as a Debian changelog; the Unreleased changes are left
+    /// out, and every release needs a date.
+    pub fn to_debian(&self, options: &DebianOptions) -> Result<String, ChangelogError> {
+        options.validate()?;
+
+        let mut out = String::new();
+        for release in self.releases.iter().filter(|r| !r.unreleased) {
+            let date: Date = release
+                .date
+                .as_deref()
+                .ok_or_else(|| {
+                    ChangelogError::Validation(format!("release {} has no date", release.version))
+                })?
+                .parse()?;
+
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!(
+                "{} ({}) {}; urgency={}\n\n",
+                options.package,
+                debian_version(&release.version)?,
+                options.distribution,
+                options.urgency
+            ));
+
+            let entries: Vec<&Entry> = release.sections.iter().flat_map(|s| &s.entries).collect();
+            if entries.is_empty() {
+                out.push_str("  * New upstream release.\n");
+            }
+            for entry in entries {
+                push_change(&mut out, &plain_text(&entry.text), "  *", "   ");
+            }
+
+            out.push_str(&format!(
+                "\n -- {}  {}\n",
+                options.maintainer,
+                date.rfc2822()
+            ));
+        }
+        Ok(out)
+    }
+
+    /// Reads a Debian changelog, with each stanza as a release and its
+    /// changes as entries of a Changed section.
+    pub fn from_debian(input: &str, locale: &'static Locale) -> Result<Changelog, ChangelogError> {
+        lazy_static! {
+            static ref HEADER: Regex = Regex::new(
+                r"^(?P<package>[a-z0-9][a-z0-9+.-]+) \((?P<version>[^() \t]+)\)(?:\s+[-+0-9a-zA-Z.]+)+;.*$"
+            )
+            .unwrap();
+            static ref TRAILER: Regex = Regex::new(r"^ -- (?P<maintainer>.+?)  (?P<date>.+)$").unwrap();
+        }
+
+        let mut changelog = Changelog::new(locale);
+        let mut release: Option<(usize, Release, Vec<String>)> = None;
+
+        for (i, line) in input.lines().enumerate() {
+            let mut finished = false;
+            let error = |message: &str| ChangelogError::Parse {
+                line: i + 1,
+                message: message.to_string(),
+            };
+
+            match &mut release {
+                None if line.trim().is_empty() => (),
+                None => {
+                    let captures = HEADER.captures(line).ok_or_else(|| {
+                        error("expected 'package (version) distribution; urgency=...'")
+                    })?;
+                    // the `~` of a prerelease is a `-` again, unless the
+                    // version has a Debian revision
+                    let version = &captures["version"];
+                    let version = if version.contains('-') {
+                        version.to_string()
+                    } else {
+                        version.replacen('~', "-", 1)
+                    };
+                    let new = Release {
+                        version,
+                        date: None,
+                        unreleased: false,
+                        yanked: false,
+                        description: String::new(),
+                        sections: vec![],
+                        style: HeadingStyle::default(),
+                    };
+                    release = Some((i + 1, new, vec![]));
+                }
+                Some((start, current, entries)) => {
+                    if let Some(captures) = TRAILER.captures(line) {
+                        let date = Date::from_rfc2822(&captures["date"])
+                            .ok_or_else(|| error("malformed date"))?;
+                        current.date = Some(date.to_string());
+
+                        let mut section = Section::new(Kind::Changed, locale);
+                        for text in entries.iter() {
+                            let entry = Entry::new(text);
+                            if !parse::is_item(&entry.text) {
+                                return Err(ChangelogError::Parse {
+                                    line: *start,
+                                    message: format!("change '{}' is not a list item", text),
+                                });
+                            }
+                            section.entries.push(entry);
+                        }
+                        if !section.entries.is_empty() {
+                            current.sections.push(section);
+                        }
+                        finished = true;
+                    } else if let Some(text) = line.strip_prefix("  * ") {
+                        entries.push(text.trim().to_string());
+                    } else if line.trim().is_empty() || line.trim_start().starts_with("[ ") {
+                        // blank lines and the names of co-maintainers
+                    } else if line.starts_with("  ") {
+                        match entries.last_mut() {
+                            Some(entry) => {
+                                entry.push(' ');
+                                entry.push_str(line.trim());
+                            }
+                            None => entries.push(line.trim().to_string()),
+                        }
+                    } else {
+                        return Err(error("change lines must be indented"));
+                    }
+                }
+            }
+
+            if finished {
+                if let Some((_, done, _)) = release.take() {
+                    changelog.releases.push(done);
+                }
+            }
+        }
+
+        if let Some((start, _, _)) = release {
+            return Err(ChangelogError::Parse {
+                line: start,
+                message: String::from("the entry has no ' -- maintainer  date' line"),
+            });
+        }
+        Ok(changelog)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANGELOG: &str = "# Changelog
+
+## [Unreleased]
+
+### Added
+
+- Not released yet
+
+## [2.0.0-rc.1] - 2017-06-20
+
+### Added
+
+- New visual identity by [@tylerfortune8](https://github.com/tylerfortune8), with a longer \
+description of what changed so that it needs more than one line.
+
+### Fixed
+
+- `version` navigation.
+
+## [v1.0.0] - 2017-06-01
+";
+
+    const DEBIAN: &str = "foo (2.0.0~rc.1) unstable; urgency=medium
+
+  * New visual identity by @tylerfortune8, with a longer description of what
+    changed so that it needs more than one line.
+  * version navigation.
+
+ -- Jane Doe <jane@example.com>  Tue, 20 Jun 2017 00:00:00 +0000
+
+foo (1.0.0) unstable; urgency=medium
+
+  * New upstream release.
+
+ -- Jane Doe <jane@example.com>  Thu, 01 Jun 2017 00:00:00 +0000
+";
+
+    fn options() -> DebianOptions {
+        DebianOptions {
+            package: String::from("foo"),
+            distribution: String::from("unstable"),
+            urgency: String::from("medium"),
+            maintainer: String::from("Jane Doe <jane@example.com>"),
+        }
+    }
+
+    /// The releases read from a Debian changelog, below an empty Unreleased.
+    fn releases(input: &str) -> Vec<Release> {
+        let mut changelog = Changelog::from_debian(input, Locale::english()).unwrap();
+        assert!(changelog.releases[0].unreleased && changelog.releases[0].is_empty());
+        changelog.releases.remove(0);
+        changelog.releases
+    }
+
+    fn entries(release: &Release) -> Vec<&str> {
+        release
+            .sections
+            .iter()
+            .flat_map(|s| &s.entries)
+            .map(|e| e.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn export() {
+        let changelog = Changelog::parse(CHANGELOG).unwrap();
+        assert_eq!(changelog.to_debian(&options()).unwrap(), DEBIAN);
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let releases = releases(DEBIAN);
+        let versions: Vec<&str> = releases.iter().map(|r| r.version.as_str()).collect();
+        assert_eq!(versions, ["2.0.0-rc.1", "1.0.0"]);
+        assert_eq!(releases[0].date.as_deref(), Some("2017-06-20"));
+        assert_eq!(
+            entries(&releases[0]),
+            [
+                "New visual identity by @tylerfortune8, with a longer description of what \
+                 changed so that it needs more than one line.",
+                "version navigation."
+            ]
+        );
+        assert!(releases[0].section(Kind::Changed).is_some());
+        assert_eq!(entries(&releases[1]), ["New upstream release."]);
+        let changelog = Changelog::from_debian(DEBIAN, Locale::english()).unwrap();
+        assert_eq!(changelog.to_debian(&options()).unwrap(), DEBIAN);
+    }
+
+    #[test]
+    fn import_sample() {
+        // as dch writes it, with a Debian revision and a co-maintainer
+        let input = "hello (2.10-3) unstable; urgency=low
+
+  [ John Smith ]
+  * Fix the build with GCC 14.
+    Closes: #1075000
+
+ -- Santiago Vila <sanvila@debian.org>  Sat, 27 Jul 2024 13:35:00 +0200
+
+hello (2.10-2) unstable; urgency=medium
+
+  * Raise debhelper compat level to 13.
+
+ -- Santiago Vila <sanvila@debian.org>  Mon, 2 Nov 2020 20:00:00 +0100
+";
+        let releases = releases(input);
+        let versions: Vec<&str> = releases.iter().map(|r| r.version.as_str()).collect();
+        assert_eq!(versions, ["2.10-3", "2.10-2"]);
+        assert_eq!(releases[0].date.as_deref(), Some("2024-07-27"));
+        assert_eq!(releases[1].date.as_deref(), Some("2020-11-02"));
+        assert_eq!(
+            entries(&releases[0]),
+            ["Fix the build with GCC 14. Closes: #1075000"]
+        );
+        assert_eq!(
+            entries(&releases[1]),
+            ["Raise debhelper compat level to 13."]
+        );
+    }
+
+    #[test]
+    fn import_errors() {
+        let missing_trailer = "foo (1.0.0) unstable; urgency=medium\n\n  * Change.\n";
+        match Changelog::from_debian(missing_trailer, Locale::english()) {
+            Err(ChangelogError::Parse { line: 1, .. }) => (),
+            result => panic!("{:?}", result),
+        }
+        let unindented = "foo (1.0.0) unstable; urgency=medium\n\n* Change.\n";
+        match Changelog::from_debian(unindented, Locale::english()) {
+            Err(ChangelogError::Parse { line: 3, .. }) => (),
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn versions() {
+        assert_eq!(debian_version("1.0.0").unwrap(), "1.0.0");
+        assert_eq!(debian_version("v1.0.0").unwrap(), "1.0.0");
+        assert_eq!(debian_version("2.0.0-rc.1").unwrap(), "2.0.0~rc.1");
+        assert_eq!(debian_version("2:1.0-beta-2").unwrap(), "2:1.0~beta-2");
+        assert!(debian_version("latest").is_err());
+    }
+
+    #[test]
+    fn invalid_options() {
+        let changelog = Changelog::parse(CHANGELOG).unwrap();
+        let invalid = [
+            DebianOptions {
+                package: String::from("Foo"),
+                ..options()
+            },
+            DebianOptions {
+                distribution: String::from("un stable"),
+                ..options()
+            },
+            DebianOptions {
+                maintainer: String::from("jane@example.com"),
+                ..options()
+            },
+        ];
+        for options in &invalid {
+            match changelog.to_debian(options) {
+                Err(ChangelogError::Validation(_)) => (),
+                result => panic!("{:?}: {:?}", options, result),
+            }
+        }
+    }
+}
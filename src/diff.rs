@@ -0,0 +1,58 @@
+//! Unified diff of a pending change, printed by `--dry-run`.
+
+use std::io::{self, IsTerminal, Write};
+
+use similar::{ChangeTag, TextDiff};
+
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+/// Prints the changes from `old` to `new` on stdout, colored when it is a
+/// terminal. Nothing is printed if there are none.
+pub fn print(filename: &str, old: &str, new: &str) -> io::Result<()> {
+    if old == new {
+        return Ok(());
+    }
+
+    let stdout = io::stdout();
+    let color = stdout.is_terminal();
+    let mut out = stdout.lock();
+    let paint = |style: &str, line: &str| {
+        if color && !style.is_empty() {
+            format!("{}{}{}", style, line, RESET)
+        } else {
+            line.to_string()
+        }
+    };
+
+    writeln!(out, "{}", paint(BOLD, &format!("--- a/{}", filename)))?;
+    writeln!(out, "{}", paint(BOLD, &format!("+++ b/{}", filename)))?;
+
+    let diff = TextDiff::from_lines(old, new);
+    for hunk in diff.unified_diff().iter_hunks() {
+        writeln!(out, "{}", paint(CYAN, &hunk.header().to_string()))?;
+        for change in hunk.iter_changes() {
+            let (sign, style) = match change.tag() {
+                ChangeTag::Delete => ('-', RED),
+                ChangeTag::Insert => ('+', GREEN),
+                ChangeTag::Equal => (' ', ""),
+            };
+            let line = change.value();
+            writeln!(
+                out,
+                "{}",
+                paint(
+                    style,
+                    &format!("{}{}", sign, line.strip_suffix('\n').unwrap_or(line))
+                )
+            )?;
+            if change.missing_newline() {
+                writeln!(out, "\\ No newline at end of file")?;
+            }
+        }
+    }
+    Ok(())
+}
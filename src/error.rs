@@ -0,0 +1,89 @@
+//! Errors returned by the library.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum ChangelogError {
+    /// Reading or writing the changelog failed.
+    Io(io::Error),
+
+    /// The input is not a changelog we understand.
+    Parse {
+        /// Line of the input, starting from 1.
+        line: usize,
+        message: String,
+    },
+
+    /// A value given to the library, such as a version or a date, is not
+    /// acceptable.
+    Validation(String),
+
+    /// The change would clash with what is already in the changelog.
+    Conflict(String),
+
+    /// A release, section or entry that was asked for does not exist.
+    NotFound(String),
+
+    /// Lint found this many problems in the changelog.
+    Lint(usize),
+}
+
+impl ChangelogError {
+    /// Exit codes of the command line tool with their meaning, as its
+    /// `--help` lists them.
+    pub const EXIT_CODES: [(i32, &'static str); 7] = [
+        (0, "success"),
+        (1, "the changelog cannot be read or written"),
+        (2, "invalid command line"),
+        (3, "the changelog cannot be parsed"),
+        (4, "invalid version, date or entry, or lint found problems"),
+        (
+            5,
+            "the change conflicts with the changelog, e.g. an existing release",
+        ),
+        (6, "release or entry not found"),
+    ];
+
+    /// Exit code of the command line tool for this error, one of
+    /// `EXIT_CODES`.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ChangelogError::Io(_) => 1,
+            ChangelogError::Parse { .. } => 3,
+            ChangelogError::Validation(_) | ChangelogError::Lint(_) => 4,
+            ChangelogError::Conflict(_) => 5,
+            ChangelogError::NotFound(_) => 6,
+        }
+    }
+}
+
+impl fmt::Display for ChangelogError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChangelogError::Io(err) => write!(f, "{}", err),
+            ChangelogError::Parse { line, message } => write!(f, "line {}: {}", line, message),
+            ChangelogError::Validation(msg) => write!(f, "invalid value: {}", msg),
+            ChangelogError::Conflict(msg) => write!(f, "conflict: {}", msg),
+            ChangelogError::NotFound(msg) => write!(f, "not found: {}", msg),
+            ChangelogError::Lint(1) => f.write_str("lint found 1 problem"),
+            ChangelogError::Lint(count) => write!(f, "lint found {} problems", count),
+        }
+    }
+}
+
+impl Error for ChangelogError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ChangelogError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ChangelogError {
+    fn from(err: io::Error) -> Self {
+        ChangelogError::Io(err)
+    }
+}
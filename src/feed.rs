@@ -0,0 +1,322 @@
+//! Atom and RSS feeds of the releases.
+
+use std::str::FromStr;
+
+use pulldown_cmark::{Event, Parser, Tag};
+
+use crate::changelog::{Changelog, Release};
+use crate::date::Date;
+use crate::error::ChangelogError;
+
+/// Id of a release in a feed, after its version only, so that it stays
+/// the same when the release is dated again or yanked, unlike its anchor.
+fn release_id(base_url: &str, release: &Release) -> String {
+    format!(
+        "{}#{}",
+        base_url,
+        escape(&release.version.replace(' ', "%20"))
+    )
+}
+
+/// Format of a feed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FeedFormat {
+    Atom,
+    Rss,
+}
+
+impl FeedFormat {
+    pub const ALL: [FeedFormat; 2] = [FeedFormat::Atom, FeedFormat::Rss];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "atom",
+            FeedFormat::Rss => "rss",
+        }
+    }
+}
+
+impl FromStr for FeedFormat {
+    type Err = ChangelogError;
+
+    fn from_str(s: &str) -> Result<FeedFormat, ChangelogError> {
+        FeedFormat::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ChangelogError::Validation(format!("unknown feed format '{}'", s)))
+    }
+}
+
+/// How `Changelog::feed_with` makes a feed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FeedOptions {
+    /// Author of an Atom feed, by default the title of the changelog.
+    pub author: Option<String>,
+}
+
+/// Escapes text for XML content and attributes.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Text of the first heading of some Markdown.
+pub(crate) fn title(markdown: &str) -> Option<String> {
+    let mut title: Option<String> = None;
+    for event in Parser::new(markdown) {
+        match (event, &mut title) {
+            (Event::Start(Tag::Heading(..)), None) => title = Some(String::new()),
+            (Event::End(Tag::Heading(..)), Some(_)) => break,
+            (Event::Text(text), Some(title)) | (Event::Code(text), Some(title)) => {
+                title.push_str(&text)
+            }
+            _ => (),
+        }
+    }
+    title.filter(|t| !t.is_empty())
+}
+
+impl Changelog {
+    /// A feed with an item per release, newest first, linking to the release
+    /// anchors of the changelog published at `base_url`.
+    ///
+    /// The Unreleased changes and releases without a valid date are left out.
+    /// Items are identified by the version of their release, which does not
+    /// change when it is yanked.
+    pub fn feed(&self, format: FeedFormat, base_url: &str) -> String {
+        self.feed_with(format, base_url, &FeedOptions::default())
+    }
+
+    /// A feed of the releases as `feed` makes it, with `options`.
+    pub fn feed_with(&self, format: FeedFormat, base_url: &str, options: &FeedOptions) -> String {
+        let title = title(&self.preamble).unwrap_or_else(|| String::from("Changelog"));
+        let author = escape(options.author.as_deref().unwrap_or(&title));
+        let title = escape(&title);
+        let base_url = escape(base_url);
+        let releases: Vec<(&Release, Date)> = self
+            .releases
+            .iter()
+            .filter(|r| !r.unreleased)
+            .filter_map(|r| Some((r, r.date.as_ref()?.parse().ok()?)))
+            .collect();
+        let updated = releases.iter().map(|(_, date)| *date).max();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        match format {
+            FeedFormat::Atom => {
+                xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+                xml.push_str(&format!("  <title>{}</title>\n", title));
+                xml.push_str(&format!("  <id>{}</id>\n", base_url));
+                xml.push_str(&format!(
+                    "  <link rel=\"alternate\" href=\"{}\"/>\n",
+                    base_url
+                ));
+                xml.push_str(&format!(
+                    "  <updated>{}</updated>\n",
+                    updated.map_or_else(|| Date::today().rfc3339(), Date::rfc3339)
+                ));
+                xml.push_str(&format!(
+                    "  <author>\n    <name>{}</name>\n  </author>\n",
+                    author
+                ));
+                for (release, date) in &releases {
+                    let link = format!("{}#{}", base_url, escape(&release.anchor()));
+                    xml.push_str("  <entry>\n");
+                    xml.push_str(&format!(
+                        "    <title>{}</title>\n",
+                        escape(&release.title())
+                    ));
+                    xml.push_str(&format!(
+                        "    <id>{}</id>\n",
+                        release_id(&base_url, release)
+                    ));
+                    xml.push_str(&format!(
+                        "    <link rel=\"alternate\" href=\"{}\"/>\n",
+                        link
+                    ));
+                    xml.push_str(&format!("    <updated>{}</updated>\n", date.rfc3339()));
+                    xml.push_str(&format!(
+                        "    <content type=\"html\">{}</content>\n",
+                        escape(&self.release_html(release))
+                    ));
+                    xml.push_str("  </entry>\n");
+                }
+                xml.push_str("</feed>\n");
+            }
+            FeedFormat::Rss => {
+                xml.push_str("<rss version=\"2.0\">\n<channel>\n");
+                xml.push_str(&format!("  <title>{}</title>\n", title));
+                xml.push_str(&format!("  <link>{}</link>\n", base_url));
+                xml.push_str(&format!("  <description>{}</description>\n", title));
+                if let Some(updated) = updated {
+                    xml.push_str(&format!(
+                        "  <lastBuildDate>{}</lastBuildDate>\n",
+                        updated.rfc2822()
+                    ));
+                }
+                for (release, date) in &releases {
+                    let link = format!("{}#{}", base_url, escape(&release.anchor()));
+                    xml.push_str("  <item>\n");
+                    xml.push_str(&format!(
+                        "    <title>{}</title>\n",
+                        escape(&release.title())
+                    ));
+                    xml.push_str(&format!("    <link>{}</link>\n", link));
+                    xml.push_str(&format!(
+                        "    <guid isPermaLink=\"false\">{}</guid>\n",
+                        release_id(&base_url, release)
+                    ));
+                    xml.push_str(&format!("    <pubDate>{}</pubDate>\n", date.rfc2822()));
+                    xml.push_str(&format!(
+                        "    <description>{}</description>\n",
+                        escape(&self.release_html(release))
+                    ));
+                    xml.push_str("  </item>\n");
+                }
+                xml.push_str("</channel>\n</rss>\n");
+            }
+        }
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHANGELOG: &str = "# Acme & Co
+
+## [Unreleased]
+
+## [1.1.0] - 2020-02-01 [YANKED]
+
+### Fixed
+
+- Crash
+
+## [1.0.0] - 2020-01-01
+
+### Added
+
+- Everything
+";
+
+    /// Elements of an XML text with their content, as `(path, content)`
+    /// pairs, e.g. `("feed/entry/id", "...")`.
+    fn elements(xml: &str) -> Vec<(String, String)> {
+        let mut path: Vec<&str> = vec![];
+        let mut elements = vec![];
+        let mut rest = xml;
+        while let Some(start) = rest.find('<') {
+            let end = start + rest[start..].find('>').unwrap();
+            let tag = &rest[start + 1..end];
+            let content = &rest[end + 1..];
+            rest = content;
+            if tag.starts_with('?') || tag.ends_with('/') {
+                continue;
+            }
+            if let Some(name) = tag.strip_prefix('/') {
+                assert_eq!(path.pop(), Some(name));
+                continue;
+            }
+            path.push(tag.split(' ').next().unwrap());
+            let text = &content[..content.find('<').unwrap()];
+            elements.push((path.join("/"), text.trim().to_string()));
+        }
+        assert!(path.is_empty());
+        elements
+    }
+
+    fn contents<'a>(elements: &'a [(String, String)], path: &str) -> Vec<&'a str> {
+        elements
+            .iter()
+            .filter(|(p, _)| p == path)
+            .map(|(_, text)| text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn atom_required_elements() {
+        let changelog = Changelog::parse(CHANGELOG).unwrap();
+        let feed = changelog.feed(FeedFormat::Atom, "https://example.com/changelog");
+        let elements = elements(&feed);
+        assert_eq!(
+            contents(&elements, "feed/id"),
+            ["https://example.com/changelog"]
+        );
+        assert_eq!(contents(&elements, "feed/title"), ["Acme &amp; Co"]);
+        assert_eq!(
+            contents(&elements, "feed/updated"),
+            ["2020-02-01T00:00:00Z"]
+        );
+        assert_eq!(contents(&elements, "feed/author/name"), ["Acme &amp; Co"]);
+        assert_eq!(
+            contents(&elements, "feed/entry/id"),
+            [
+                "https://example.com/changelog#1.1.0",
+                "https://example.com/changelog#1.0.0"
+            ]
+        );
+        assert_eq!(
+            contents(&elements, "feed/entry/title"),
+            ["1.1.0 [YANKED]", "1.0.0"]
+        );
+        assert_eq!(
+            contents(&elements, "feed/entry/updated"),
+            ["2020-02-01T00:00:00Z", "2020-01-01T00:00:00Z"]
+        );
+    }
+
+    #[test]
+    fn atom_author() {
+        let changelog = Changelog::parse(CHANGELOG).unwrap();
+        let options = FeedOptions {
+            author: Some(String::from("Jane <jane@example.com>")),
+        };
+        let feed = changelog.feed_with(FeedFormat::Atom, "https://example.com/", &options);
+        assert_eq!(
+            contents(&elements(&feed), "feed/author/name"),
+            ["Jane &lt;jane@example.com&gt;"]
+        );
+
+        let changelog = Changelog::parse("## [1.0.0] - 2020-01-01\n").unwrap();
+        let feed = changelog.feed(FeedFormat::Atom, "https://example.com/");
+        assert_eq!(
+            contents(&elements(&feed), "feed/author/name"),
+            ["Changelog"]
+        );
+    }
+
+    #[test]
+    fn rss_items() {
+        let changelog = Changelog::parse(CHANGELOG).unwrap();
+        let feed = changelog.feed(FeedFormat::Rss, "https://example.com/changelog");
+        let elements = elements(&feed);
+        assert_eq!(contents(&elements, "rss/channel/title"), ["Acme &amp; Co"]);
+        assert_eq!(
+            contents(&elements, "rss/channel/item/guid"),
+            [
+                "https://example.com/changelog#1.1.0",
+                "https://example.com/changelog#1.0.0"
+            ]
+        );
+        assert_eq!(
+            contents(&elements, "rss/channel/item/link"),
+            [
+                "https://example.com/changelog#110---2020-02-01-yanked",
+                "https://example.com/changelog#100---2020-01-01"
+            ]
+        );
+    }
+}
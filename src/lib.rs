@@ -0,0 +1,41 @@
+//! Reading and updating changelogs in the
+//! [Keep a Changelog](https://keepachangelog.com/) format.
+//!
+//! A changelog is parsed into a [`Changelog`](struct.Changelog.html), whose
+//! releases, sections and entries can be queried and changed, then written
+//! back as Markdown.
+
+#[macro_use]
+extern crate lazy_static;
+
+mod asciidoc;
+mod backport;
+mod changelog;
+pub mod date;
+pub mod debian;
+mod error;
+pub mod feed;
+mod lint;
+pub mod locale;
+mod man;
+mod markdown;
+mod migrate;
+pub mod notes;
+mod parse;
+pub mod releases;
+pub mod render;
+pub mod rpm;
+mod rst;
+pub mod schema;
+mod site;
+pub mod text;
+pub mod version;
+
+pub use crate::changelog::{
+    Changelog, DateStyle, Entry, HeadingStyle, Kind, Release, ReleaseOptions, ReleaseOrder,
+    Section, VersionStyle,
+};
+pub use crate::date::Date;
+pub use crate::error::ChangelogError;
+pub use crate::lint::Lint;
+pub use crate::locale::Locale;
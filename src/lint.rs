@@ -0,0 +1,334 @@
+//! Checks of a changelog against the Keep a Changelog conventions.
+
+use std::fmt;
+
+use crate::changelog::{Changelog, DateStyle, HeadingStyle, Kind, Release, Section, VersionStyle};
+use crate::date::Date;
+use crate::version::{SemVer, Version, VersionScheme};
+
+/// A problem found in a changelog.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lint {
+    /// Version of the release it was found in, `None` for the whole
+    /// changelog.
+    pub version: Option<String>,
+
+    pub message: String,
+
+    /// Whether `Changelog::fix` corrects it.
+    pub fixable: bool,
+}
+
+impl Lint {
+    fn new(release: Option<&Release>, message: String, fixable: bool) -> Lint {
+        Lint {
+            version: release.map(|r| r.version.clone()),
+            message,
+            fixable,
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "[{}] {}", version, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// Whether a release heading is written as Keep a Changelog does, as in
+/// `[1.0.0] - 2020-01-01`.
+fn is_standard(release: &Release) -> bool {
+    release.style.version == VersionStyle::Bracketed
+        && (release.date.is_none() || release.style.date == DateStyle::Hyphen)
+}
+
+/// Position of a section in a release: standard ones in the order of
+/// `Kind::ALL`, then the others.
+fn rank(section: &Section) -> usize {
+    section.kind.map_or(Kind::ALL.len(), |kind| kind as usize)
+}
+
+impl Changelog {
+    /// Problems of the changelog, in the order they appear, for a project
+    /// following SemVer.
+    pub fn lint(&self) -> Vec<Lint> {
+        self.lint_with_scheme(&SemVer)
+    }
+
+    /// Problems of the changelog, in the order they appear, for a project
+    /// whose versions follow `scheme`.
+    pub fn lint_with_scheme(&self, scheme: &dyn VersionScheme) -> Vec<Lint> {
+        let mut lints = vec![];
+        // highest version so far of each maintenance line
+        let mut newer: Vec<(Option<&str>, Version, &str)> = vec![];
+
+        let unreleased: Vec<&Release> = self.releases.iter().filter(|r| r.unreleased).collect();
+        let mut repeated = false;
+        for (i, release) in unreleased.iter().enumerate() {
+            let line = self.line(release);
+            if unreleased[..i]
+                .iter()
+                .any(|r| self.same_line(self.line(r), line))
+            {
+                repeated = true;
+                lints.push(Lint::new(
+                    None,
+                    format!("{} is repeated", release.version),
+                    false,
+                ));
+            }
+        }
+        if !repeated {
+            let top = self.releases.iter().take_while(|r| r.unreleased).count();
+            for release in self.releases[top..].iter().filter(|r| r.unreleased) {
+                lints.push(Lint::new(
+                    None,
+                    format!("{} is not above the releases", release.version),
+                    true,
+                ));
+            }
+        }
+
+        for (i, release) in self.releases.iter().enumerate() {
+            let lint = |message: String, fixable: bool| Lint::new(Some(release), message, fixable);
+
+            if !release.unreleased {
+                match &release.date {
+                    None => lints.push(lint(String::from("the release has no date"), false)),
+                    Some(date) if date.parse::<Date>().is_err() => {
+                        lints.push(lint(format!("'{}' is not a YYYY-MM-DD date", date), false))
+                    }
+                    _ => (),
+                }
+                if self.releases[..i]
+                    .iter()
+                    .any(|r| !r.unreleased && r.version == release.version)
+                {
+                    lints.push(lint(String::from("the version is used twice"), false));
+                }
+                match scheme.parse(&release.version) {
+                    Err(_) => lints.push(lint(
+                        format!("'{}' is not a {} version", release.version, scheme.name()),
+                        false,
+                    )),
+                    Ok(version) => {
+                        // versions go down within each line, whatever the
+                        // order of the lines
+                        let line = self.line_of_version(&release.version);
+                        match newer.iter_mut().find(|(l, _, _)| *l == line) {
+                            Some((_, newer, newer_version)) => {
+                                if version > *newer {
+                                    lints.push(lint(
+                                        format!(
+                                            "the release is out of order, {} above it is lower",
+                                            newer_version
+                                        ),
+                                        false,
+                                    ));
+                                }
+                                *newer = version;
+                                *newer_version = &release.version;
+                            }
+                            None => newer.push((line, version, &release.version)),
+                        }
+                    }
+                }
+            }
+
+            if !is_standard(release) {
+                let standard = Release {
+                    style: HeadingStyle::default(),
+                    ..release.clone()
+                };
+                lints.push(lint(
+                    format!(
+                        "heading '{}' should be '{}'",
+                        release.heading(),
+                        standard.heading()
+                    ),
+                    true,
+                ));
+            }
+
+            for (j, section) in release.sections.iter().enumerate() {
+                match section.kind {
+                    None => lints.push(lint(
+                        format!("'{}' is not a standard section", section.title),
+                        false,
+                    )),
+                    Some(kind) => {
+                        let heading = self.locale.heading(kind);
+                        if section.title != heading {
+                            lints.push(lint(
+                                format!("section '{}' should be '{}'", section.title, heading),
+                                true,
+                            ));
+                        }
+                        if release.sections[..j].iter().any(|s| s.kind == Some(kind)) {
+                            lints.push(lint(format!("section '{}' is repeated", heading), true));
+                        }
+                    }
+                }
+                if section.entries.is_empty() && section.description.is_empty() {
+                    lints.push(lint(format!("section '{}' is empty", section.title), true));
+                }
+            }
+            if release
+                .sections
+                .windows(2)
+                .any(|pair| rank(&pair[0]) > rank(&pair[1]))
+            {
+                lints.push(lint(String::from("sections are out of order"), true));
+            }
+        }
+        lints
+    }
+
+    /// Corrects the problems that `lint` reports as fixable.
+    pub fn fix(&mut self) {
+        // Unreleased releases go on top, unless one is repeated and which
+        // to keep is not clear
+        let lines: Vec<Option<&str>> = self
+            .releases
+            .iter()
+            .filter(|r| r.unreleased)
+            .map(|r| self.line(r))
+            .collect();
+        if (0..lines.len()).all(|i| !lines[..i].iter().any(|l| self.same_line(*l, lines[i]))) {
+            let (unreleased, releases): (Vec<Release>, Vec<Release>) =
+                self.releases.drain(..).partition(|r| r.unreleased);
+            self.releases = unreleased.into_iter().chain(releases).collect();
+        }
+
+        let locale = self.locale;
+        for release in &mut self.releases {
+            if !is_standard(release) {
+                // the link of a heading becomes a reference definition
+                if let VersionStyle::Linked(url) = &release.style.version {
+                    if !self
+                        .links
+                        .iter()
+                        .any(|(l, _)| l.eq_ignore_ascii_case(&release.version))
+                    {
+                        self.links.push((release.version.clone(), url.clone()));
+                    }
+                }
+                release.style = HeadingStyle::default();
+            }
+
+            let mut sections: Vec<Section> = vec![];
+            for mut section in release.sections.drain(..) {
+                if section.entries.is_empty() && section.description.is_empty() {
+                    continue;
+                }
+                let kind = match section.kind {
+                    Some(kind) => kind,
+                    None => {
+                        sections.push(section);
+                        continue;
+                    }
+                };
+                match sections.iter_mut().find(|s| s.kind == Some(kind)) {
+                    Some(first) => {
+                        if !section.description.is_empty() {
+                            if !first.description.is_empty() {
+                                first.description.push_str("\n\n");
+                            }
+                            first.description.push_str(&section.description);
+                        }
+                        first.entries.append(&mut section.entries);
+                    }
+                    None => {
+                        section.title = locale.heading(kind).to_string();
+                        sections.push(section);
+                    }
+                }
+            }
+            sections.sort_by_key(rank);
+            release.sections = sections;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINES: &str = "# Changelog
+
+## [Unreleased]
+
+## [Unreleased 1.x]
+
+## [2.1.0] - 2020-06-01
+
+## [1.4.3] - 2020-05-15
+
+## [2.0.0] - 2020-05-01
+
+## [1.4.2] - 2020-04-01
+";
+
+    fn messages(input: &str) -> Vec<String> {
+        Changelog::parse(input)
+            .unwrap()
+            .lint()
+            .iter()
+            .map(Lint::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn lines_in_any_order() {
+        assert_eq!(messages(LINES), Vec::<String>::new());
+        let by_version = LINES.replace(
+            "## [1.4.3] - 2020-05-15\n\n## [2.0.0] - 2020-05-01\n",
+            "## [2.0.0] - 2020-05-01\n\n## [1.4.3] - 2020-05-15\n",
+        );
+        assert_eq!(messages(&by_version), Vec::<String>::new());
+    }
+
+    #[test]
+    fn out_of_order_in_a_line() {
+        let input = LINES.replace("[1.4.2] - 2020-04-01", "[1.4.4] - 2020-04-01");
+        assert_eq!(
+            messages(&input),
+            ["[1.4.4] the release is out of order, 1.4.3 above it is lower"]
+        );
+        let input = LINES.replace("[2.0.0] - 2020-05-01", "[2.2.0] - 2020-05-01");
+        assert_eq!(
+            messages(&input),
+            ["[2.2.0] the release is out of order, 2.1.0 above it is lower"]
+        );
+    }
+
+    #[test]
+    fn without_the_line_versions_are_one_line() {
+        let input = LINES.replace("## [Unreleased 1.x]\n\n", "");
+        assert_eq!(
+            messages(&input),
+            ["[2.0.0] the release is out of order, 1.4.3 above it is lower"]
+        );
+    }
+
+    #[test]
+    fn unreleased_lines() {
+        let input = LINES.replace("## [2.0.0]", "## [Unreleased 1.X]\n\n## [2.0.0]");
+        assert_eq!(messages(&input), ["Unreleased 1.X is repeated"]);
+
+        let input = LINES
+            .replace("## [Unreleased 1.x]\n\n", "")
+            .replace("## [2.0.0]", "## [Unreleased 1.x]\n\n## [2.0.0]");
+        assert_eq!(
+            messages(&input),
+            ["Unreleased 1.x is not above the releases"]
+        );
+        assert!(Changelog::parse(&input).unwrap().lint()[0].fixable);
+        let mut changelog = Changelog::parse(&input).unwrap();
+        changelog.fix();
+        assert_eq!(changelog, Changelog::parse(LINES).unwrap());
+    }
+}
@@ -0,0 +1,142 @@
+//! Translated headings for changelogs written in other languages.
+//!
+//! Every locale maps the six canonical section kinds, in `fields_order`
+//! order, and the label of the upcoming release to the headings used in
+//! the file. The same table is used to recognize existing headings and to
+//! write new ones.
+
+use crate::changelog::Kind;
+
+/// Section and release headings for one language.
+#[derive(Debug, PartialEq)]
+pub struct Locale {
+    /// Code selected with `--locale`.
+    pub code: &'static str,
+
+    /// Label of the upcoming release, as in `## [Unreleased]`.
+    pub unreleased: &'static str,
+
+    /// Section headings, in the same order as the canonical kinds.
+    pub fields_order: [&'static str; 6],
+}
+
+pub const LOCALES: [Locale; 5] = [
+    Locale {
+        code: "en",
+        unreleased: "Unreleased",
+        fields_order: [
+            "Added",
+            "Changed",
+            "Deprecated",
+            "Removed",
+            "Fixed",
+            "Security",
+        ],
+    },
+    Locale {
+        code: "fr",
+        unreleased: "Non publié",
+        fields_order: [
+            "Ajouté",
+            "Modifié",
+            "Obsolète",
+            "Supprimé",
+            "Corrigé",
+            "Sécurité",
+        ],
+    },
+    Locale {
+        code: "de",
+        unreleased: "Unveröffentlicht",
+        fields_order: [
+            "Hinzugefügt",
+            "Geändert",
+            "Veraltet",
+            "Entfernt",
+            "Behoben",
+            "Sicherheit",
+        ],
+    },
+    Locale {
+        code: "es",
+        unreleased: "Sin publicar",
+        fields_order: [
+            "Añadido",
+            "Cambiado",
+            "Obsoleto",
+            "Eliminado",
+            "Corregido",
+            "Seguridad",
+        ],
+    },
+    Locale {
+        code: "it",
+        unreleased: "Non rilasciato",
+        fields_order: [
+            "Aggiunto",
+            "Modificato",
+            "Deprecato",
+            "Rimosso",
+            "Corretto",
+            "Sicurezza",
+        ],
+    },
+];
+
+impl Locale {
+    /// Looks up a locale by its code, e.g. `"fr"`.
+    pub fn find(code: &str) -> Option<&'static Locale> {
+        LOCALES.iter().find(|l| l.code.eq_ignore_ascii_case(code))
+    }
+
+    /// The English locale, used when none is given.
+    pub fn english() -> &'static Locale {
+        &LOCALES[0]
+    }
+
+    /// Heading of a section kind.
+    pub fn heading(&self, kind: Kind) -> &'static str {
+        self.fields_order[kind as usize]
+    }
+
+    /// Kind of a section heading.
+    ///
+    /// Headings are compared case-insensitively; the English heading is
+    /// accepted too, as translated changelogs often keep some of them.
+    pub fn kind(&self, heading: &str) -> Option<Kind> {
+        let heading = heading.trim().to_lowercase();
+        self.fields_order
+            .iter()
+            .chain(LOCALES[0].fields_order.iter())
+            .position(|x| x.to_lowercase() == heading)
+            .map(|p| Kind::ALL[p % Kind::ALL.len()])
+    }
+
+    /// Whether a release version is this locale's (or the English)
+    /// Unreleased label, with or without a maintenance line.
+    pub fn is_unreleased(&self, version: &str) -> bool {
+        self.unreleased_line(version).is_some()
+    }
+
+    /// Maintenance line of an Unreleased label, e.g. `1.x` for
+    /// `Unreleased 1.x`: `Some(None)` for the main line, and `None` if the
+    /// version is not an Unreleased label.
+    pub fn unreleased_line<'a>(&self, version: &'a str) -> Option<Option<&'a str>> {
+        let version = version.trim();
+        [self.unreleased, LOCALES[0].unreleased]
+            .iter()
+            .find_map(|label| {
+                let rest = version
+                    .get(..label.len())
+                    .filter(|start| start.to_lowercase() == label.to_lowercase())
+                    .map(|_| &version[label.len()..])?;
+                if rest.is_empty() {
+                    Some(None)
+                } else if rest.starts_with(char::is_whitespace) {
+                    Some(Some(rest.trim_start()))
+                } else {
+                    None
+                }
+            })
+    }
+}
@@ -1,292 +1,610 @@
-use clap::{App, Arg, SubCommand};
-use pulldown_cmark::{html, CowStr, Event, InlineStr, Options, Parser, Tag};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 
+use std::collections::hash_map::RandomState;
+use std::env;
 use std::fs;
-use std::io::Write;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+use changelog::debian::DebianOptions;
+use changelog::feed::{FeedFormat, FeedOptions};
+use changelog::locale::LOCALES;
+use changelog::notes::TEMPLATES;
+use changelog::releases::ReleaseHost;
+use changelog::render::{RenderFormat, RenderOptions};
+use changelog::rpm::RpmOptions;
+use changelog::schema::Format;
+use changelog::text::TextOptions;
+use changelog::version::{self, PrereleaseMode, SCHEMES};
+use changelog::{
+    Changelog, ChangelogError, Date, Kind, Lint, Locale, ReleaseOptions, ReleaseOrder,
+};
+
+mod diff;
+
+const TEMPLATE_HELP: &str = "Print release notes from a template file, or a built-in one: \
+                             markdown, slack or email";
+
+const ENTRY_TEMPLATE: &str = "# Write the entry above, on as many lines as needed.\n\
+                              # Lines starting with '#' are ignored.";
+
+/// The exit codes section of `--help`.
+fn exit_codes() -> String {
+    let mut help = String::from("EXIT CODES:");
+    for (code, meaning) in ChangelogError::EXIT_CODES.iter() {
+        help.push_str(&format!("\n    {}    {}", code, meaning));
+    }
+    help
+}
 
-#[macro_use]
-extern crate lazy_static;
-use regex::Regex;
+/// Prints the problems found by `lint`, failing if there are any.
+fn report(filename: &str, lints: &[Lint]) -> Result<(), ChangelogError> {
+    for lint in lints {
+        let fix = if lint.fixable { " (fixable)" } else { "" };
+        println!("{}: {}{}", filename, lint, fix);
+    }
+    match lints.len() {
+        0 => Ok(()),
+        n => Err(ChangelogError::Lint(n)),
+    }
+}
 
-use std::collections::VecDeque;
+/// Options of a Debian export, the maintainer defaulting to the
+/// `DEBFULLNAME` and `DEBEMAIL` variables as for `dch`.
+fn debian_options(matches: &ArgMatches) -> Result<DebianOptions, ChangelogError> {
+    let maintainer = match matches.value_of("maintainer") {
+        Some(maintainer) => maintainer.to_string(),
+        None => match (env::var("DEBFULLNAME"), env::var("DEBEMAIL")) {
+            (Ok(name), Ok(email)) => format!("{} <{}>", name, email),
+            _ => {
+                return Err(ChangelogError::Validation(String::from(
+                    "--maintainer is needed, or DEBFULLNAME and DEBEMAIL",
+                )))
+            }
+        },
+    };
+    let package = matches.value_of("package").ok_or_else(|| {
+        ChangelogError::Validation(String::from("--package is needed for a Debian changelog"))
+    })?;
+
+    Ok(DebianOptions {
+        package: package.to_string(),
+        distribution: matches.value_of("distribution").unwrap().to_string(),
+        urgency: matches.value_of("urgency").unwrap().to_string(),
+        maintainer,
+    })
+}
 
-mod markdown;
+/// Options of an RPM export, the packager defaulting to the
+/// `RPM_PACKAGER` variable as for `rpmdev-packager`.
+fn rpm_options(matches: &ArgMatches) -> Result<RpmOptions, ChangelogError> {
+    let packager = match matches.value_of("packager") {
+        Some(packager) => packager.to_string(),
+        None => env::var("RPM_PACKAGER").map_err(|_| {
+            ChangelogError::Validation(String::from("--packager is needed, or RPM_PACKAGER"))
+        })?,
+    };
 
-const HEADING_RELEASE: u32 = 2;
-const HEADING_SECTION: u32 = 3;
+    Ok(RpmOptions {
+        packager,
+        release: matches.value_of("release").unwrap().to_string(),
+        kinds: matches.is_present("kind-prefix"),
+    })
+}
 
-struct Changelog<'c> {
-    parser: Parser<'c>,
-    buffer: VecDeque<Event<'c>>,
-    options: &'c CliOptions,
-    fields_order: Vec<&'c str>,
-    done_section: bool,
-    // rendered_option: String,
-    string_buffer: String,
+/// Layout of text on stdout: colors are on for a terminal unless
+/// `NO_COLOR` is set, and lines fit its `COLUMNS`.
+fn text_options(matches: &ArgMatches) -> Result<TextOptions, ChangelogError> {
+    let terminal = io::stdout().is_terminal();
+    let color = match matches.value_of("color").unwrap() {
+        "always" => true,
+        "never" => false,
+        _ => terminal && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty()),
+    };
+    let width = match matches.value_of("width") {
+        Some(width) => width
+            .parse()
+            .map_err(|_| ChangelogError::Validation(format!("'{}' is not a width", width)))?,
+        None if terminal => env::var("COLUMNS")
+            .ok()
+            .and_then(|columns| columns.parse().ok())
+            .unwrap_or(80),
+        None => 80,
+    };
+    Ok(TextOptions { width, color })
 }
 
-// impl Changelog{
-//     fn parse() -> &'static str{
-//         return "ciao"
-//     }
-// }
-
-impl<'c> Changelog<'c> {
-    fn new(markdown_input: &'c String, options: &'c CliOptions) -> Changelog<'c> {
-        let parser = Parser::new_with_broken_link_callback(
-            &markdown_input,
-            Options::empty(),
-            Some(&|norm, raw| None),
-        );
-        Changelog {
-            parser,
-            buffer: VecDeque::with_capacity(4),
-            options,
-            fields_order: vec![
-                "Added",
-                "Changed",
-                "Deprecated",
-                "Removed",
-                "Fixed",
-                "Security",
-            ],
-            done_section: false,
-            string_buffer: String::from(""),
-        }
-    }
+/// Arguments of the text layout, for `show` and `render`.
+fn text_args<'a, 'b>() -> [Arg<'a, 'b>; 2] {
+    [
+        Arg::with_name("color")
+            .long("color")
+            .value_name("WHEN")
+            .help("Style text: always, never, or auto for a terminal without NO_COLOR")
+            .possible_values(&["auto", "always", "never"])
+            .default_value("auto"),
+        Arg::with_name("width")
+            .long("width")
+            .value_name("COLUMNS")
+            .help("Width text is wrapped at, defaults to the terminal's"),
+    ]
+}
 
-    fn enqueue_item(&mut self, item: &'c String) {
-        self.buffer.push_front(Event::Start(Tag::Item));
-        self.buffer.push_front(Event::Text(CowStr::Borrowed(item)));
-        self.buffer.push_front(Event::End(Tag::Item));
+fn run(matches: &ArgMatches, filename: &str) -> Result<(), ChangelogError> {
+    let locale = matches
+        .value_of("locale")
+        .and_then(Locale::find)
+        .unwrap_or_else(Locale::english);
+    let scheme = matches
+        .value_of("scheme")
+        .and_then(version::scheme)
+        .unwrap_or(SCHEMES[0]);
+
+    if let ("init", Some(smatches)) = matches.subcommand() {
+        if Path::new(filename).exists() && !smatches.is_present("overwrite") {
+            return Err(ChangelogError::Conflict(format!(
+                "{} already exists, use --overwrite to replace it",
+                filename
+            )));
+        }
+        return Changelog::new(locale).write(fs::File::create(filename)?);
     }
 
-    fn pushback_item(&mut self, item: &'c str) {
-        self.buffer.push_back(Event::End(Tag::Item));
-        self.buffer.push_back(Event::Text(CowStr::Borrowed(item)));
-        self.buffer.push_back(Event::Start(Tag::Item));
+    if let ("import", Some(smatches)) = matches.subcommand() {
+        let mut input = String::new();
+        match smatches.value_of("input").unwrap() {
+            "-" => io::stdin().read_to_string(&mut input).map(|_| ())?,
+            path => input = fs::read_to_string(path)?,
+        }
+        let from = smatches.value_of("from").unwrap();
+        let new = if let Ok(host) = from.parse::<ReleaseHost>() {
+            let (changelog, notes) = Changelog::from_releases(&input, host, locale)?;
+            for note in notes {
+                eprintln!("{}: {}", smatches.value_of("input").unwrap(), note);
+            }
+            changelog
+        } else if from == "debian" {
+            Changelog::from_debian(&input, locale)?
+        } else {
+            Changelog::import(&input, from.parse::<Format>()?, locale)?
+        }
+        .to_string();
+
+        let exists = Path::new(filename).exists();
+        if smatches.is_present("dry-run") {
+            let old = if exists {
+                fs::read_to_string(filename)?
+            } else {
+                String::new()
+            };
+            diff::print(filename, &old, &new)?;
+        } else if exists && !smatches.is_present("overwrite") {
+            return Err(ChangelogError::Conflict(format!(
+                "{} already exists, use --overwrite to replace it",
+                filename
+            )));
+        } else {
+            fs::write(filename, new)?;
+        }
+        return Ok(());
     }
 
-    fn pushback_heading(&mut self, item: &'c str, heading: u32) {
-        self.buffer.push_back(Event::End(Tag::Heading(heading)));
-        self.buffer.push_back(Event::Text(CowStr::Borrowed(item)));
-        self.buffer.push_back(Event::Start(Tag::Heading(heading)));
+    if let ("migrate", Some(smatches)) = matches.subcommand() {
+        let input = smatches.value_of("input").unwrap_or(filename);
+        let exists = input != filename && Path::new(filename).exists();
+        if exists && !smatches.is_present("overwrite") && !smatches.is_present("dry-run") {
+            return Err(ChangelogError::Conflict(format!(
+                "{} already exists, use --overwrite to replace it",
+                filename
+            )));
+        }
+        let old = fs::read_to_string(input)?;
+        let (changelog, notes) = Changelog::migrate(&old, locale)?;
+        for note in notes {
+            eprintln!("{}: {}", input, note);
+        }
+        let new = changelog.to_string();
+        if smatches.is_present("dry-run") {
+            let old = if input == filename || exists {
+                fs::read_to_string(filename)?
+            } else {
+                String::new()
+            };
+            diff::print(filename, &old, &new)?;
+        } else {
+            fs::write(filename, new)?;
+        }
+        return Ok(());
     }
-}
 
-impl<'c> Iterator for Changelog<'c> {
-    type Item = Event<'c>;
+    let old = fs::read_to_string(filename)?;
+    let mut changelog = Changelog::parse_with_locale(&old, locale)?;
 
-    fn next(&mut self) -> Option<Event<'c>> {
-        lazy_static! {
-            static ref RELEASE_PATTERN: Regex =
-                Regex::new(r"^\[(?P<version>.*)\](?: +- +(?P<date>.*) *)?$").unwrap();
+    let (command, smatches) = matches.subcommand();
+    let smatches = match smatches {
+        Some(smatches) => smatches,
+        None => return Ok(()),
+    };
+    match command {
+        "release" => {
+            let date = smatches
+                .value_of("date")
+                .map_or_else(|| Date::today().to_string(), String::from);
+            // versions are written without their `v`, and only checked
+            // against a scheme given with --scheme
+            let version = smatches.value_of("version").unwrap();
+            let version = match scheme.parse(version) {
+                Ok(version) => version.to_string(),
+                Err(_) if matches.occurrences_of("scheme") == 0 => version.to_string(),
+                Err(err) => return Err(err),
+            };
+            let options = ReleaseOptions {
+                line: smatches.value_of("maintenance-line").map(String::from),
+                order: smatches.value_of("order").unwrap().parse()?,
+            };
+            changelog.release_with(&version, &date, &options)?;
+            if smatches.is_present("collapse-prereleases") {
+                let mode = smatches
+                    .value_of("collapse-prereleases")
+                    .map_or(Ok(PrereleaseMode::Keep), str::parse)?;
+                changelog.collapse_prereleases(&version, scheme, mode)?;
+            }
         }
-
-        if !self.buffer.is_empty() {
-            let next = self.buffer.pop_back();
-            return next;
+        "yank" => {
+            changelog.yank(smatches.value_of("version").unwrap())?;
         }
-
-        let next = self.parser.next();
-
-        match next {
-            Some(Event::Start(Tag::Heading(2))) if !self.done_section => {
-                // we are in the first release heading, is it
-                // "Unreleased" already?
-
-                let release = self.parser.next();
-
-                match &release {
-                    Some(Event::Text(text)) => {
-                        // a text-only / broken link release specification
-                        // consume iterator until heading's end
-
-                        let mut release_vec = Vec::with_capacity(4);
-
-                        release_vec.push(text.to_owned());
-                        self.buffer.push_front(release.unwrap());
-
-                        loop {
-                            let release = self.parser.next();
-                            match &release {
-                                Some(Event::Text(text)) => {
-                                    release_vec.push(text.clone());
-                                    self.buffer.push_front(release.unwrap());
-                                }
-                                _ => {
-                                    self.buffer.push_front(release.unwrap());
-                                    let raw_release = release_vec.join("");
-                                    let parsed_release = RELEASE_PATTERN.captures(&raw_release);
-                                    match parsed_release {
-                                        Some(x) => {
-                                            match x.name("version") {
-                                                Some(x) => {
-                                                    let release_string = x.as_str();
-                                                    // println!("version: <{}>", release_string);
-                                                    if release_string.to_ascii_lowercase()
-                                                        != "unreleased"
-                                                    {
-                                                        // println!("non unre");
-                                                        self.buffer.push_back(Event::Start(
-                                                            Tag::Heading(HEADING_RELEASE),
-                                                        ));
-
-                                                        self.buffer
-                                                            .push_back(Event::End(Tag::List(None)));
-
-                                                        self.pushback_item(&self.options.argument);
-
-                                                        self.buffer.push_back(Event::Start(
-                                                            Tag::List(None),
-                                                        ));
-
-                                                        self.pushback_heading(
-                                                            self.fields_order[self
-                                                                .fields_order
-                                                                .iter()
-                                                                .position(|&x| {
-                                                                    x.eq_ignore_ascii_case(
-                                                                        &self.options.action,
-                                                                    )
-                                                                })
-                                                                .unwrap()],
-                                                            HEADING_SECTION,
-                                                        );
-
-                                                        self.buffer.push_back(Event::End(
-                                                            Tag::Heading(HEADING_RELEASE),
-                                                        ));
-                                                        self.buffer.push_back(Event::Text(
-                                                            CowStr::Borrowed("[Unreleased]"),
-                                                        ));
-                                                    }
-                                                }
-                                                None => {
-                                                    println!(
-                                                        "cannot parse version, bailing out..."
-                                                    );
-                                                }
-                                            }
-
-                                            // if x.
-                                        }
-                                        None => println!("malformed header: {}", raw_release),
-                                    }
-                                    break;
-                                }
-                            }
-                        }
-
-                        // println!("BROKEN LINK {}", &release_vec.join(""));
+        "export" => {
+            let mut data = match smatches.value_of("format").unwrap() {
+                "debian" => changelog.to_debian(&debian_options(smatches)?)?,
+                "rpm" => changelog.to_rpm(&rpm_options(smatches)?)?,
+                format => changelog.export(format.parse::<Format>()?)?,
+            };
+            if !data.ends_with('\n') {
+                data.push('\n');
+            }
+            io::stdout().write_all(data.as_bytes())?;
+            return Ok(());
+        }
+        "feed" => {
+            let format = smatches.value_of("format").unwrap().parse::<FeedFormat>()?;
+            let options = FeedOptions {
+                author: smatches.value_of("author").map(String::from),
+            };
+            let feed =
+                changelog.feed_with(format, smatches.value_of("base-url").unwrap(), &options);
+            io::stdout().write_all(feed.as_bytes())?;
+            return Ok(());
+        }
+        "render" => {
+            return match smatches
+                .value_of("format")
+                .unwrap()
+                .parse::<RenderFormat>()?
+            {
+                RenderFormat::Text => {
+                    changelog.render_text(None, text_options(smatches)?, io::stdout().lock())
+                }
+                format => {
+                    let options = RenderOptions {
+                        name: smatches.value_of("name").map(String::from),
+                    };
+                    changelog.render_with(format, &options, io::stdout().lock())
+                }
+            };
+        }
+        "show" => {
+            let release = match smatches.value_of("version") {
+                Some(version) => {
+                    let release = if version == "latest" {
+                        changelog.latest_version(scheme, smatches.is_present("prereleases"))
+                    } else {
+                        changelog.release_by_name(version)
+                    };
+                    Some(release.ok_or_else(|| {
+                        ChangelogError::NotFound(format!("release {} not found", version))
+                    })?)
+                }
+                None => None,
+            };
+            if let (Some(release), Some(template)) = (release, smatches.value_of("template")) {
+                let template = match TEMPLATES.iter().find(|(name, _)| *name == template) {
+                    Some((_, builtin)) if !Path::new(template).exists() => builtin.to_string(),
+                    _ => fs::read_to_string(template)?,
+                };
+                // the project is named after its directory by default
+                let project = match smatches.value_of("project") {
+                    Some(project) => project.to_string(),
+                    None => fs::canonicalize(filename)?
+                        .parent()
+                        .and_then(|dir| dir.file_name())
+                        .map_or_else(String::new, |name| name.to_string_lossy().into_owned()),
+                };
+                let notes = changelog.release_notes(release, &project, &template)?;
+                io::stdout().write_all(notes.as_bytes())?;
+                return Ok(());
+            }
+            return changelog.render_text(release, text_options(smatches)?, io::stdout().lock());
+        }
+        "entries" => {
+            let version = smatches
+                .value_of("version")
+                .unwrap_or(changelog.locale().unreleased);
+            let release = changelog.release_by_name(version).ok_or_else(|| {
+                ChangelogError::NotFound(format!("release {} not found", version))
+            })?;
+            let width = release
+                .sections
+                .iter()
+                .map(|s| s.title.chars().count())
+                .max()
+                .unwrap_or(0);
+            let mut out = io::stdout().lock();
+            for section in &release.sections {
+                for entry in &section.entries {
+                    // further lines of an entry are aligned with its first
+                    let indent = format!("\n{:1$}", "", width + 12);
+                    let text = entry
+                        .text
+                        .lines()
+                        .map(str::trim_start)
+                        .collect::<Vec<_>>()
+                        .join(&indent);
+                    writeln!(out, "{}  {:3$}  {}", entry.id(), section.title, text, width)?;
+                }
+            }
+            return Ok(());
+        }
+        "site" => {
+            let templates = smatches.value_of("templates").map(Path::new);
+            return changelog
+                .write_site(Path::new(smatches.value_of("output").unwrap()), templates);
+        }
+        "backport" => {
+            let from = changelog_at(smatches.value_of("from").unwrap(), filename, locale)?;
+            changelog.backport(
+                &from,
+                smatches.value_of("entry").unwrap(),
+                smatches.value_of("reference"),
+                smatches.value_of("maintenance-line"),
+            )?;
+        }
+        "remove" => {
+            changelog.remove_entry(
+                entry_release(&changelog, smatches),
+                smatches.value_of("id").unwrap(),
+            )?;
+        }
+        "edit" => {
+            let version = entry_release(&changelog, smatches);
+            match (smatches.value_of("id"), smatches.values_of("text")) {
+                (Some(id), Some(text)) => {
+                    let text: Vec<&str> = text.collect();
+                    changelog.edit_entry(version, id, &text.join(" "))?;
+                }
+                (Some(id), None) => {
+                    let template = format!(
+                        "{}\n# The entry is removed if it is left empty.",
+                        ENTRY_TEMPLATE
+                    );
+                    let text = write_entry(&changelog.entry(version, id)?.text, &template)?;
+                    if text.is_empty() {
+                        changelog.remove_entry(version, id)?;
+                    } else {
+                        changelog.edit_entry(version, id, &text)?;
                     }
-                    _ => (),
                 }
-
-                // print!("\nAIEEI {:?}", release);
-                // let release = self.parser.next();
-                // print!("\nAIEEI {:?}", release);
-                // let release = self.parser.next();
-                // print!("\nAIEEI {:?}", release);
-                // let release = self.parser.next();
-                // print!("\nAIEEI {:?}", release);
-                // let release = self.parser.next();
-                // print!("\nAIEEI {:?}", release);
-
-                self.done_section = true;
+                _ => {
+                    let body = changelog
+                        .release_by_name(version)
+                        .map(|r| r.body())
+                        .ok_or_else(|| {
+                            ChangelogError::NotFound(format!("release {} not found", version))
+                        })?;
+                    let (path, edited) = edit_text(&body)?;
+                    if let Err(err) = changelog.set_release_body(version, &edited) {
+                        eprintln!("{}: the changes are kept in {}", filename, path.display());
+                        return Err(err);
+                    }
+                    fs::remove_file(path)?;
+                }
             }
-            _ => (),
         }
+        "move" => {
+            let kind = smatches.value_of("to").unwrap().parse::<Kind>()?;
+            changelog.move_entry(
+                entry_release(&changelog, smatches),
+                smatches.value_of("id").unwrap(),
+                kind,
+            )?;
+        }
+        "lint" if !smatches.is_present("fix") => {
+            return report(filename, &changelog.lint_with_scheme(scheme));
+        }
+        "lint" => changelog.fix(),
+        "fmt" => (),
+        kind => {
+            let kind = kind.parse::<Kind>()?;
+            let text = match smatches.values_of("line") {
+                Some(line) => line.collect::<Vec<_>>().join(" "),
+                None => {
+                    let title = changelog.locale().heading(kind);
+                    let template =
+                        format!("# New entry of the {} section.\n{}", title, ENTRY_TEMPLATE);
+                    write_entry("", &template)?
+                }
+            };
+            changelog.add_line_entry(kind, &text, smatches.value_of("maintenance-line"))?;
+        }
+    }
+
+    let new = changelog.to_string();
+    if smatches.is_present("dry-run") {
+        diff::print(filename, &old, &new)?;
+    } else if new != old {
+        fs::write(filename, new)?;
+    }
+
+    if command == "lint" {
+        report(filename, &changelog.lint_with_scheme(scheme))?;
+    }
+    Ok(())
+}
+
+/// Changelog `filename` as it is at revision `rev` of its git repository.
+fn changelog_at(
+    rev: &str,
+    filename: &str,
+    locale: &'static Locale,
+) -> Result<Changelog, ChangelogError> {
+    let path = Path::new(filename);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path
+        .file_name()
+        .map_or_else(|| filename.into(), |name| name.to_string_lossy());
+    // `./` makes the path relative to the directory rather than the repository
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .arg("show")
+        .arg(format!("{}:./{}", rev, name))
+        .output()?;
+    if !output.status.success() {
+        return Err(ChangelogError::NotFound(format!(
+            "{} at {}: {}",
+            filename,
+            rev,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let input = String::from_utf8(output.stdout).map_err(|_| ChangelogError::Parse {
+        line: 1,
+        message: format!("{} at {} is not UTF-8", filename, rev),
+    })?;
+    Changelog::parse_with_locale(&input, locale)
+}
+
+/// A new file in the temporary directory, readable only by the user.
+///
+/// Its name is random and it must not exist, so that no one else sharing
+/// the directory can guess it, or have it written through a symbolic link.
+fn temp_file() -> Result<(PathBuf, fs::File), ChangelogError> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    loop {
+        // the keys of a new `RandomState` are random
+        let suffix = RandomState::new().build_hasher().finish();
+        let path = env::temp_dir().join(format!("CHANGELOG-{:016x}.md", suffix));
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
 
-        // if let Some(Event::Start(Tag::Heading(3))) = &next {
-        //     let title_event = self.parser.next().expect("Error getting heading title");
-
-        //     if let Event::Text(header_title) = &title_event {
-        //         // calculate
-        //         let position_in_order = self
-        //             .fields_order
-        //             .iter()
-        //             .position(|&x| x == header_title.to_string());
-
-        //         let command_position = self
-        //             .fields_order
-        //             .iter()
-        //             .position(|&x| x.eq_ignore_ascii_case(&self.options.action));
-
-        //         if !self.done_section && position_in_order == command_position {
-        //             //pop a couple items from iterator
-        //             let end_of_heading = self.parser.next().unwrap();
-        //             let start_of_list = self.parser.next().unwrap();
-
-        //             self.buffer.push_front(next.unwrap());
-        //             self.buffer.push_front(title_event);
-        //             // end of heading
-        //             self.buffer.push_front(end_of_heading);
-
-        //             self.buffer.push_front(start_of_list);
-
-        //             self.enqueue_item(&self.options.argument);
-        //             // self.buffer.push_front(Event::Start(Tag::Item));
-        //             // self.buffer
-        //             //     .push_front(Event::Text(CowStr::Borrowed(&self.options.argument)));
-        //             // self.buffer.push_front(Event::End(Tag::Item));
-
-        //             self.done_section = true;
-        //             return self.next();
-        //         }
-        //     // else if position_in_order > command_position && !self.done_section {
-        //     //     // println!("baugigi");
-
-        //     //     self.buffer.push(title_event);
-        //     //     self.buffer.push(next.unwrap());
-        //     //     self.buffer.push(Event::End(Tag::List(None)));
-        //     //     self.buffer.push(Event::End(Tag::Item));
-        //     //     self.buffer
-        //     //         .push(Event::Text(CowStr::Borrowed(&self.options.argument)));
-        //     //     self.buffer.push(Event::Start(Tag::Item));
-        //     //     self.buffer.push(Event::Start(Tag::List(None)));
-        //     //     self.buffer.push(Event::End(Tag::Heading(3)));
-        //     //     self.buffer.push(Event::Text(CowStr::Borrowed(
-        //     //         &self.fields_order[command_position.unwrap()],
-        //     //     )));
-        //     //     self.buffer.push(Event::Start(Tag::Heading(3)));
-        //     //     self.done_section = true;
-        //     //     return self.next();
-        //     // }
-
-        //     // println!(
-        //     //     "[XXX] - following: {} (pos {:?}, req: {:?})",
-        //     //     header_title, position_in_order, command_position
-        //     // );
-        //     } else {
-        //         println!("bauscia");
-        //     }
-
-        //     self.buffer.push_front(next.unwrap());
-        //     self.buffer.push_front(title_event);
-
-        //     // self.buffer.push(Event::Start(Tag::Heading(*xx)));
-        //     // self.buffer.push(Event::End(Tag::Heading(*xx)));
-        //     // self.buffer
-        //     //     .push(Event::Text(CowStr::Borrowed("[culocane]")));
-        //     // self.buffer.push(Event::Start(Tag::Heading(*xx)));
-        //     return self.next();
-        // };
-
-        next
+/// Lets the user change `text` in `$VISUAL` or `$EDITOR`, `vi` by default,
+/// returning the file it is in and what it became.
+fn edit_text(text: &str) -> Result<(PathBuf, String), ChangelogError> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| String::from("vi"));
+    let (path, mut file) = temp_file()?;
+    file.write_all(text.as_bytes())?;
+    drop(file);
+
+    // the editor can come with arguments, e.g. `code --wait`
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$1\"", editor))
+        .arg(&editor)
+        .arg(&path)
+        .status()?;
+    if !status.success() {
+        fs::remove_file(&path)?;
+        return Err(ChangelogError::Io(io::Error::other(format!(
+            "{} exited with {}",
+            editor, status
+        ))));
     }
+    let edited = fs::read_to_string(&path)?;
+    Ok((path, edited))
+}
+
+/// Text of an entry written in the editor, without the lines of `template`
+/// and the other comment lines, which start with `#`.
+fn write_entry(text: &str, template: &str) -> Result<String, ChangelogError> {
+    let (path, edited) = edit_text(&format!("{}\n\n{}\n", text, template))?;
+    fs::remove_file(path)?;
+    let lines: Vec<&str> = edited
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect();
+    Ok(lines.join("\n").trim().to_string())
+}
+
+/// Release of the entry a subcommand changes, the Unreleased one by
+/// default.
+fn entry_release<'a>(changelog: &Changelog, matches: &'a ArgMatches) -> &'a str {
+    matches
+        .value_of("release")
+        .unwrap_or(changelog.locale().unreleased)
+}
+
+/// Arguments naming an entry, for `remove`, `edit` and `move`.
+fn entry_args<'a, 'b>() -> [Arg<'a, 'b>; 2] {
+    [
+        Arg::with_name("id")
+            .help("Id of the entry, as listed by entries")
+            .required(true),
+        Arg::with_name("release")
+            .long("release")
+            .value_name("VERSION")
+            .help("Release of the entry, e.g. 1.2.0 or Unreleased 1.x, defaults to Unreleased"),
+    ]
 }
 
-struct CliOptions {
-    action: String,
-    argument: String,
+/// Maintenance line of an entry or a release, e.g. `1.x`.
+fn line_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("maintenance-line")
+        .long("line")
+        .value_name("LINE")
+        .help("Maintenance line, e.g. 1.x, whose Unreleased release is used")
+}
+
+fn dry_run<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("dry-run")
+        .long("dry-run")
+        .help("Print the changes as a diff instead of writing them")
 }
 
 fn main() {
+    let mut formats: Vec<&str> = Format::ALL.iter().map(|f| f.name()).collect();
+    formats.push("debian");
+    let mut export_formats = formats.clone();
+    export_formats.push("rpm");
+    let mut import_formats = formats.clone();
+    import_formats.extend(ReleaseHost::ALL.iter().map(|h| h.name()));
+
+    // the entry is optional for edit, which edits the whole release without it
+    let [id, release] = entry_args();
+    let exit_codes = exit_codes();
+
     let matches = App::new("CHACHACHA")
         .about("\nDoes awesome things")
         .version("0-muku")
         .author("Alessandro -oggei- Ogier <alessandro.ogier@gmail.com>")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .after_help(exit_codes.as_str())
         .arg(
             Arg::with_name("filename")
                 .short("f")
@@ -295,6 +613,23 @@ fn main() {
                 .help("Sets changelog's path")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("locale")
+                .short("l")
+                .long("locale")
+                .value_name("LOCALE")
+                .help("Sets the language of section and Unreleased headings")
+                .possible_values(&LOCALES.iter().map(|l| l.code).collect::<Vec<_>>())
+                .default_value("en"),
+        )
+        .arg(
+            Arg::with_name("scheme")
+                .long("scheme")
+                .value_name("SCHEME")
+                .help("Sets the scheme versions follow, to check and order them")
+                .possible_values(&SCHEMES.iter().map(|s| s.name()).collect::<Vec<_>>())
+                .default_value("semver"),
+        )
         .arg(
             Arg::with_name("v")
                 .short("v")
@@ -304,32 +639,348 @@ fn main() {
         .subcommand(
             SubCommand::with_name("added")
                 .about("Add an 'added' entry")
-                .arg(Arg::with_name("line").help("Line to add").multiple(true)),
+                .arg(Arg::with_name("line").help("Line to add, written in the editor if it is not given").multiple(true))
+                .arg(line_arg())
+                .arg(dry_run()),
         )
         .subcommand(
             SubCommand::with_name("changed")
                 .about("Add a 'changed' entry")
-                .arg(Arg::with_name("line").help("Line to add").multiple(true)),
+                .arg(Arg::with_name("line").help("Line to add, written in the editor if it is not given").multiple(true))
+                .arg(line_arg())
+                .arg(dry_run()),
         )
         .subcommand(
             SubCommand::with_name("deprecated")
                 .about("Add a 'deprecated' entry")
-                .arg(Arg::with_name("line").help("Line to add").multiple(true)),
+                .arg(Arg::with_name("line").help("Line to add, written in the editor if it is not given").multiple(true))
+                .arg(line_arg())
+                .arg(dry_run()),
         )
         .subcommand(
             SubCommand::with_name("fixed")
                 .about("Add a 'fixed' entry")
-                .arg(Arg::with_name("line").help("Line to add").multiple(true)),
+                .arg(Arg::with_name("line").help("Line to add, written in the editor if it is not given").multiple(true))
+                .arg(line_arg())
+                .arg(dry_run()),
         )
         .subcommand(
             SubCommand::with_name("removed")
                 .about("Add a 'removed' entry")
-                .arg(Arg::with_name("line").help("Line to add").multiple(true)),
+                .arg(Arg::with_name("line").help("Line to add, written in the editor if it is not given").multiple(true))
+                .arg(line_arg())
+                .arg(dry_run()),
         )
         .subcommand(
             SubCommand::with_name("security")
                 .about("Add a 'security' entry")
-                .arg(Arg::with_name("line").help("Line to add").multiple(true)),
+                .arg(Arg::with_name("line").help("Line to add, written in the editor if it is not given").multiple(true))
+                .arg(line_arg())
+                .arg(dry_run()),
+        )
+        .subcommand(
+            SubCommand::with_name("entries")
+                .about("List the entries of a release with their ids")
+                .arg(Arg::with_name("version").help("Version of the release, defaults to Unreleased")),
+        )
+        .subcommand(
+            SubCommand::with_name("remove")
+                .about("Remove an entry")
+                .args(&entry_args())
+                .arg(dry_run()),
+        )
+        .subcommand(
+            SubCommand::with_name("edit")
+                .about("Replace the text of an entry, or edit the Unreleased changes in $VISUAL or $EDITOR")
+                .arg(id.required(false))
+                .arg(release)
+                .arg(
+                    Arg::with_name("text")
+                        .help("New text of the entry, written in the editor if it is not given")
+                        .multiple(true),
+                )
+                .arg(dry_run()),
+        )
+        .subcommand(
+            SubCommand::with_name("move")
+                .about("Move an entry to another section")
+                .args(&entry_args())
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .value_name("KIND")
+                        .help("Kind of the section to move the entry to")
+                        .possible_values(&Kind::ALL.iter().map(|k| k.name()).collect::<Vec<_>>())
+                        .required(true),
+                )
+                .arg(dry_run()),
+        )
+        .subcommand(
+            SubCommand::with_name("release")
+                .about("Turn the Unreleased changes into a release")
+                .arg(
+                    Arg::with_name("version")
+                        .help("Version of the release")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("date")
+                        .long("date")
+                        .value_name("DATE")
+                        .help("Release date, defaults to today")
+                        .takes_value(true),
+                )
+                .arg(line_arg())
+                .arg(
+                    Arg::with_name("order")
+                        .long("order")
+                        .value_name("ORDER")
+                        .help("Where the release goes: below the Unreleased releases, or by version")
+                        .possible_values(&ReleaseOrder::ALL.iter().map(|o| o.name()).collect::<Vec<_>>())
+                        .default_value("date"),
+                )
+                .arg(
+                    Arg::with_name("collapse-prereleases")
+                        .long("collapse-prereleases")
+                        .value_name("MODE")
+                        .help("Merge the entries of the release's prereleases into it, then keep, remove or fold them")
+                        .possible_values(&PrereleaseMode::ALL.iter().map(|m| m.name()).collect::<Vec<_>>())
+                        .min_values(0)
+                        .require_equals(true),
+                )
+                .arg(dry_run()),
+        )
+        .subcommand(
+            SubCommand::with_name("yank")
+                .about("Mark a release as yanked")
+                .arg(
+                    Arg::with_name("version")
+                        .help("Version of the release")
+                        .required(true),
+                )
+                .arg(dry_run()),
+        )
+        .subcommand(
+            SubCommand::with_name("backport")
+                .about("Add an entry of the changelog at another git revision to the Unreleased changes")
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .value_name("REV")
+                        .help("Git revision to read the changelog at, e.g. main")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("entry")
+                        .long("entry")
+                        .value_name("ENTRY")
+                        .help("Id of the entry, or its text or part of it")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("reference")
+                        .long("reference")
+                        .value_name("REF")
+                        .help("Reference recorded with the entry, e.g. the commit cherry-picked"),
+                )
+                .arg(line_arg())
+                .arg(dry_run()),
+        )
+        .subcommand(
+            SubCommand::with_name("lint")
+                .about("Check the changelog against the Keep a Changelog conventions")
+                .arg(
+                    Arg::with_name("fix")
+                        .long("fix")
+                        .help("Fix the problems that can be fixed"),
+                )
+                .arg(dry_run().requires("fix")),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Print the changelog as data")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Data format, or a packaging changelog")
+                        .possible_values(&export_formats)
+                        .default_value("json"),
+                )
+                .arg(
+                    Arg::with_name("package")
+                        .long("package")
+                        .value_name("NAME")
+                        .help("Package name, for debian"),
+                )
+                .arg(
+                    Arg::with_name("distribution")
+                        .long("distribution")
+                        .value_name("DIST")
+                        .help("Distribution of the uploads, for debian")
+                        .default_value("unstable"),
+                )
+                .arg(
+                    Arg::with_name("urgency")
+                        .long("urgency")
+                        .value_name("URGENCY")
+                        .help("Urgency of the uploads, for debian")
+                        .default_value("medium"),
+                )
+                .arg(
+                    Arg::with_name("maintainer")
+                        .long("maintainer")
+                        .value_name("NAME <EMAIL>")
+                        .help("Maintainer, for debian, defaults to $DEBFULLNAME <$DEBEMAIL>"),
+                )
+                .arg(
+                    Arg::with_name("packager")
+                        .long("packager")
+                        .value_name("NAME <EMAIL>")
+                        .help("Packager, for rpm, defaults to $RPM_PACKAGER"),
+                )
+                .arg(
+                    Arg::with_name("release")
+                        .long("release")
+                        .value_name("RELEASE")
+                        .help("Release appended to the versions, for rpm")
+                        .default_value("1"),
+                )
+                .arg(
+                    Arg::with_name("kind-prefix")
+                        .long("kind-prefix")
+                        .help("Prefix entries with their section, e.g. [Fixed], for rpm"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Write the changelog from data, as printed by export")
+                .arg(
+                    Arg::with_name("from")
+                        .long("from")
+                        .value_name("FORMAT")
+                        .help("Data format, a packaging changelog, or a dump of the releases API")
+                        .possible_values(&import_formats)
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("input")
+                        .help("File to read, - for the standard input")
+                        .default_value("-"),
+                )
+                .arg(
+                    Arg::with_name("overwrite")
+                        .long("overwrite")
+                        .help("Overwrite an existing file"),
+                )
+                .arg(dry_run()),
+        )
+        .subcommand(
+            SubCommand::with_name("migrate")
+                .about("Rewrite a changelog written in another style, reporting what is not recognized")
+                .arg(
+                    Arg::with_name("input")
+                        .help("File to read, defaults to the changelog itself"),
+                )
+                .arg(
+                    Arg::with_name("overwrite")
+                        .long("overwrite")
+                        .help("Overwrite an existing file other than the input"),
+                )
+                .arg(dry_run()),
+        )
+        .subcommand(
+            SubCommand::with_name("feed")
+                .about("Print an Atom or RSS feed of the releases")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Feed format")
+                        .possible_values(&FeedFormat::ALL.iter().map(|f| f.name()).collect::<Vec<_>>())
+                        .default_value("atom"),
+                )
+                .arg(
+                    Arg::with_name("base-url")
+                        .long("base-url")
+                        .value_name("URL")
+                        .help("URL of the published changelog, which items link to")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("author")
+                        .long("author")
+                        .value_name("NAME")
+                        .help("Author of an Atom feed, defaults to the changelog's title"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("render")
+                .about("Print the changelog as text or in another markup language")
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Markup language")
+                        .possible_values(&RenderFormat::ALL.iter().map(|f| f.name()).collect::<Vec<_>>())
+                        .default_value("text"),
+                )
+                .arg(
+                    Arg::with_name("name")
+                        .long("name")
+                        .value_name("NAME")
+                        .help("Name of the man page, e.g. foo-changelog, defaults to its title"),
+                )
+                .args(&text_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("show")
+                .about("Print the changelog, or a release, as text")
+                .arg(Arg::with_name("version").help("Version of the release, Unreleased, or latest"))
+                .arg(
+                    Arg::with_name("prereleases")
+                        .long("prereleases")
+                        .help("Let latest be a prerelease")
+                        .requires("version"),
+                )
+                .arg(
+                    Arg::with_name("template")
+                        .long("template")
+                        .value_name("FILE")
+                        .help(TEMPLATE_HELP)
+                        .requires("version"),
+                )
+                .arg(
+                    Arg::with_name("project")
+                        .long("project")
+                        .value_name("NAME")
+                        .help("Name of the project in release notes, defaults to its directory's")
+                        .requires("template"),
+                )
+                .args(&text_args()),
+        )
+        .subcommand(
+            SubCommand::with_name("site")
+                .about("Generate a static site of the changelog")
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .value_name("DIR")
+                        .help("Directory to write the site to")
+                        .default_value("public"),
+                )
+                .arg(
+                    Arg::with_name("templates")
+                        .long("templates")
+                        .value_name("DIR")
+                        .help("Directory of templates replacing the default ones"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("fmt")
+                .about("Rewrite the changelog in the standard layout")
+                .arg(dry_run()),
         )
         .subcommand(
             SubCommand::with_name("init")
@@ -340,31 +991,18 @@ fn main() {
                         .help("Overwrite an existing file"),
                 ),
         )
-        .get_matches();
-
-    let (subcommand, smatches) = matches.subcommand();
+        .get_matches_safe()
+        .unwrap_or_else(|err| {
+            if err.use_stderr() {
+                eprintln!("{}", err.message);
+                process::exit(2);
+            }
+            err.exit()
+        });
 
-    let mut diocane = String::from("");
-    for merda in smatches.unwrap().values_of("line").unwrap() {
-        diocane.push_str(&format!(" {}", &merda));
+    let filename = matches.value_of("filename").unwrap_or("CHANGELOG.md");
+    if let Err(err) = run(&matches, filename) {
+        eprintln!("{}: {}", filename, err);
+        process::exit(err.exit_code());
     }
-
-    let markdown_input =
-        fs::read_to_string("CHANGELOG.md").expect("Something went wrong reading the file");
-
-    let options = CliOptions {
-        action: String::from(subcommand),
-        argument: String::from(diocane.trim()),
-    };
-
-    let changelog = Changelog::new(&markdown_input, &options);
-
-    // Write to anything implementing the `Write` trait. This could also be a file
-    // or network socket.
-    let stdout = std::io::stdout();
-    let mut handle = stdout.lock();
-    handle.write_all(b"\nHTML output:\n").unwrap();
-
-    // html::write_html(&mut handle, changelog).unwrap();
-    markdown::write(&mut handle, changelog).unwrap();
 }
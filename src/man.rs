@@ -0,0 +1,314 @@
+//! Man page renderer that takes an iterator of events as input.
+//!
+//! The page is written with the requests of the `man` macros: its name goes
+//! to `.TH` and, with the title of the changelog, to the NAME section,
+//! releases are `.SH` sections and their sections `.SS` subsections. Raw HTML has no equivalent and is left out.
+
+use std::io::{self, Write};
+
+use pulldown_cmark::Event::*;
+use pulldown_cmark::{Event, HeadingLevel, LinkType, Tag};
+
+use crate::render::{Output, StrWrite, WriteWrapper};
+
+struct ManWriter<I, W> {
+    /// Iterator supplying events.
+    iter: I,
+
+    out: Output<W>,
+
+    /// Whether the events are left out, as for the title of the page.
+    skip: bool,
+
+    /// Whether the page title was written, so that further first level
+    /// headings are sections.
+    title: bool,
+
+    /// Whether a section was started, so that what comes before the first
+    /// one goes to a DESCRIPTION section.
+    in_section: bool,
+
+    /// Whether the events are in a heading, where links are only text.
+    in_heading: bool,
+
+    /// Whether the events are in a code block, which is not filled.
+    in_code: bool,
+
+    /// Whether the next paragraph is the first of a list item.
+    item_start: bool,
+
+    /// Next number of each open list, `None` for bullet lists.
+    lists: Vec<Option<u64>>,
+
+    /// Destination of each open link, written after its text.
+    links: Vec<String>,
+}
+
+/// Escapes text for roff: backslashes, and hyphens so that options and
+/// URLs are not broken at them.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\e").replace('-', "\\-")
+}
+
+/// Escapes and double quotes an argument of a request.
+fn quote(text: &str) -> String {
+    format!("\"{}\"", escape(text).replace('"', "\\(dq"))
+}
+
+impl<'a, I, W> ManWriter<I, W>
+where
+    I: Iterator<Item = Event<'a>>,
+    W: StrWrite,
+{
+    fn new(iter: I, writer: W) -> Self {
+        Self {
+            iter,
+            out: Output::new(writer),
+            skip: false,
+            title: false,
+            in_section: false,
+            in_heading: false,
+            in_code: false,
+            item_start: false,
+            lists: vec![],
+            links: vec![],
+        }
+    }
+
+    /// Writes a request on a line of its own.
+    fn request(&mut self, request: &str) -> io::Result<()> {
+        self.out.end_line()?;
+        self.out.write(request)?;
+        self.out.write_newline()
+    }
+
+    /// Writes escaped text; lines starting with a control character are
+    /// guarded so that they are not taken for requests.
+    fn write_text(&mut self, text: &str) -> io::Result<()> {
+        if self.skip {
+            return Ok(());
+        }
+        for (i, line) in escape(text).split('\n').enumerate() {
+            if i > 0 {
+                self.out.write_newline()?;
+            }
+            let line = if self.out.end_newline && !self.in_code {
+                line.trim_start()
+            } else {
+                line
+            };
+            if self.out.end_newline && (line.starts_with('.') || line.starts_with('\'')) {
+                self.out.write("\\&")?;
+            }
+            self.out.write(line)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a font change, unless the events are left out.
+    fn write_font(&mut self, font: &str) -> io::Result<()> {
+        if !self.skip {
+            self.out.write(font)?;
+        }
+        Ok(())
+    }
+
+    pub fn run(mut self, name: &str, title: &str, date: &str) -> io::Result<()> {
+        self.request(&format!(
+            ".TH {} 7 {}",
+            quote(&name.to_uppercase()),
+            quote(date)
+        ))?;
+        // what `whatis` reads, as `name \- title`
+        self.request(".SH NAME")?;
+        self.request(&format!("{} \\- {}", escape(name), escape(title)))?;
+        while let Some(event) = self.iter.next() {
+            match event {
+                Start(tag) => {
+                    self.start_tag(tag)?;
+                }
+                End(tag) => {
+                    self.end_tag(tag)?;
+                }
+                Text(text) => {
+                    self.write_text(&text)?;
+                }
+                Code(text) => {
+                    self.write_font("\\fB")?;
+                    self.write_text(&text)?;
+                    self.write_font("\\fR")?;
+                }
+                Html(_) => (),
+                SoftBreak => {
+                    self.write_text("\n")?;
+                }
+                HardBreak => {
+                    self.request(".br")?;
+                }
+                Rule => {
+                    self.request(".sp")?;
+                }
+                FootnoteReference(name) => {
+                    self.write_text(&format!("[{}]", name))?;
+                }
+                TaskListMarker(true) => {
+                    self.write_text("[x] ")?;
+                }
+                TaskListMarker(false) => {
+                    self.write_text("[ ] ")?;
+                }
+            }
+        }
+        self.out.end_line()
+    }
+
+    /// Writes the start of a man page element.
+    fn start_tag(&mut self, tag: Tag<'a>) -> io::Result<()> {
+        if !self.skip && !self.in_section && !matches!(tag, Tag::Heading(..)) {
+            self.in_section = true;
+            self.request(".SH DESCRIPTION")?;
+        }
+        match tag {
+            Tag::Paragraph => {
+                if self.item_start {
+                    self.item_start = false;
+                    Ok(())
+                } else if self.lists.is_empty() {
+                    self.request(".PP")
+                } else {
+                    self.request(".sp")
+                }
+            }
+            Tag::Heading(HeadingLevel::H1, _, _) if !self.title => {
+                self.title = true;
+                self.skip = true;
+                Ok(())
+            }
+            Tag::Heading(level, _, _) => {
+                self.in_heading = true;
+                self.in_section = true;
+                self.out.end_line()?;
+                match level {
+                    HeadingLevel::H1 | HeadingLevel::H2 => self.out.write(".SH "),
+                    _ => self.out.write(".SS "),
+                }
+            }
+            Tag::Table(_) => self.request(".PP"),
+            Tag::TableHead | Tag::TableRow => Ok(()),
+            Tag::TableCell => self.write_text(" "),
+            Tag::BlockQuote => self.request(".RS 4"),
+            Tag::CodeBlock(_) => {
+                if self.lists.is_empty() {
+                    self.request(".PP")?;
+                } else {
+                    self.request(".sp")?;
+                }
+                self.item_start = false;
+                self.request(".RS 4")?;
+                self.request(".nf")?;
+                self.in_code = true;
+                Ok(())
+            }
+            Tag::List(start) => {
+                if !self.lists.is_empty() {
+                    self.request(".RS")?;
+                }
+                self.lists.push(start);
+                Ok(())
+            }
+            Tag::Item => {
+                let request = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!(".IP {}. 4", *number - 1)
+                    }
+                    _ => String::from(".IP \\(bu 2"),
+                };
+                self.request(&request)?;
+                self.item_start = true;
+                Ok(())
+            }
+            Tag::Emphasis => self.write_font("\\fI"),
+            Tag::Strong => self.write_font("\\fB"),
+            Tag::Strikethrough => Ok(()),
+            Tag::Link(LinkType::Autolink, _, _) | Tag::Link(LinkType::Email, _, _) => {
+                self.links.push(String::new());
+                Ok(())
+            }
+            Tag::Link(_, dest, _) | Tag::Image(_, dest, _) => {
+                self.links.push(dest.to_string());
+                Ok(())
+            }
+            Tag::FootnoteDefinition(name) => {
+                self.request(".PP")?;
+                self.write_text(&format!("[{}] ", name))
+            }
+        }
+    }
+
+    fn end_tag(&mut self, tag: Tag) -> io::Result<()> {
+        match tag {
+            Tag::Heading(..) if self.skip => {
+                self.skip = false;
+            }
+            Tag::Heading(..) => {
+                self.in_heading = false;
+                self.out.end_line()?;
+            }
+            Tag::TableRow | Tag::TableHead => {
+                self.out.end_line()?;
+            }
+            Tag::Paragraph | Tag::Table(_) | Tag::FootnoteDefinition(_) => (),
+            Tag::TableCell => {
+                self.write_text(" ")?;
+                self.request(".br")?;
+            }
+            Tag::BlockQuote => {
+                self.request(".RE")?;
+            }
+            Tag::CodeBlock(_) => {
+                self.in_code = false;
+                self.request(".fi")?;
+                self.request(".RE")?;
+            }
+            Tag::List(_) => {
+                self.lists.pop();
+                if !self.lists.is_empty() {
+                    self.request(".RE")?;
+                }
+            }
+            Tag::Item => {
+                self.item_start = false;
+            }
+            Tag::Emphasis | Tag::Strong => {
+                self.write_font("\\fR")?;
+            }
+            Tag::Strikethrough => (),
+            Tag::Link(..) | Tag::Image(..) => {
+                let dest = self.links.pop().unwrap_or_default();
+                if !dest.is_empty() && !self.in_heading {
+                    self.write_text(&format!(" <{}>", dest))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Iterate over an `Iterator` of `Event`s, generate a man page for each
+/// `Event`, and write it out to a writable stream.
+///
+/// The page is named `name`, describes `title` and is dated `date`.
+pub(crate) fn write<'a, I, W>(
+    writer: W,
+    iter: I,
+    name: &str,
+    title: &str,
+    date: &str,
+) -> io::Result<()>
+where
+    I: Iterator<Item = Event<'a>>,
+    W: Write,
+{
+    ManWriter::new(iter, WriteWrapper(writer)).run(name, title, date)
+}
@@ -1,93 +1,33 @@
-//! HTML renderer that takes an iterator of events as input.
+//! Markdown renderer that takes an iterator of events as input.
+//!
+//! `Html` events are written verbatim, which is also how Markdown that must
+//! not be rewritten is passed through: one ending with a newline is a block,
+//! continued by the `Html` events right after it, as pulldown-cmark emits
+//! one per line of an HTML block.
 
-use std::collections::HashMap;
-use std::fmt::{Arguments, Write as FmtWrite};
-use std::io::{self, ErrorKind, Write};
+use std::io::{self, Write};
 
-use pulldown_cmark::CowStr;
 use pulldown_cmark::Event::*;
-use pulldown_cmark::{Alignment, CodeBlockKind, Event, InlineStr, LinkType, Tag};
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, LinkType, Tag};
 
-enum TableState {
-    Head,
-    Body,
-}
-
-/// This wrapper exists because we can't have both a blanket implementation
-/// for all types implementing `Write` and types of the for `&mut W` where
-/// `W: StrWrite`. Since we need the latter a lot, we choose to wrap
-/// `Write` types.
-struct WriteWrapper<W>(W);
-
-/// Trait that allows writing string slices. This is basically an extension
-/// of `std::io::Write` in order to include `String`.
-pub(crate) trait StrWrite {
-    fn write_str(&mut self, s: &str) -> io::Result<()>;
-
-    fn write_fmt(&mut self, args: Arguments) -> io::Result<()>;
-}
-
-impl<W> StrWrite for WriteWrapper<W>
-where
-    W: Write,
-{
-    #[inline]
-    fn write_str(&mut self, s: &str) -> io::Result<()> {
-        self.0.write_all(s.as_bytes())
-    }
-
-    #[inline]
-    fn write_fmt(&mut self, args: Arguments) -> io::Result<()> {
-        self.0.write_fmt(args)
-    }
-}
-
-impl<'w> StrWrite for String {
-    #[inline]
-    fn write_str(&mut self, s: &str) -> io::Result<()> {
-        self.push_str(s);
-        Ok(())
-    }
-
-    #[inline]
-    fn write_fmt(&mut self, args: Arguments) -> io::Result<()> {
-        // FIXME: translate fmt error to io error?
-        FmtWrite::write_fmt(self, args).map_err(|_| ErrorKind::Other.into())
-    }
-}
+use crate::render::{Output, StrWrite, WriteWrapper};
 
-impl<W> StrWrite for &'_ mut W
-where
-    W: StrWrite,
-{
-    #[inline]
-    fn write_str(&mut self, s: &str) -> io::Result<()> {
-        (**self).write_str(s)
-    }
-
-    #[inline]
-    fn write_fmt(&mut self, args: Arguments) -> io::Result<()> {
-        (**self).write_fmt(args)
-    }
-}
-
-struct HtmlWriter<'a, I, W> {
+struct MarkdownWriter<I, W> {
     /// Iterator supplying events.
     iter: I,
 
-    /// Writer to write to.
-    writer: W,
+    out: Output<W>,
 
-    /// Whether or not the last write wrote a newline.
-    end_newline: bool,
+    /// Whether the last event was a block of raw HTML or Markdown.
+    html_block: bool,
+
+    /// Next number of each open list, `None` for bullet lists.
+    lists: Vec<Option<u64>>,
 
-    table_state: TableState,
     table_alignments: Vec<Alignment>,
-    table_cell_index: usize,
-    numbers: HashMap<CowStr<'a>, usize>,
 }
 
-impl<'a, I, W> HtmlWriter<'a, I, W>
+impl<'a, I, W> MarkdownWriter<I, W>
 where
     I: Iterator<Item = Event<'a>>,
     W: StrWrite,
@@ -95,34 +35,19 @@ where
     fn new(iter: I, writer: W) -> Self {
         Self {
             iter,
-            writer,
-            end_newline: true,
-            table_state: TableState::Head,
+            out: Output::new(writer),
+            html_block: false,
+            lists: vec![],
             table_alignments: vec![],
-            table_cell_index: 0,
-            numbers: HashMap::new(),
         }
     }
 
-    /// Writes a new line.
-    fn write_newline(&mut self) -> io::Result<()> {
-        self.end_newline = true;
-        self.writer.write_str("\n")
-    }
-
-    /// Writes a buffer, and tracks whether or not a newline was written.
-    #[inline]
-    fn write(&mut self, s: &str) -> io::Result<()> {
-        self.writer.write_str(s)?;
-
-        if !s.is_empty() {
-            self.end_newline = s.ends_with('\n');
-        }
-        Ok(())
-    }
-
     pub fn run(mut self) -> io::Result<()> {
         while let Some(event) = self.iter.next() {
+            if self.html_block && !matches!(event, Html(_)) {
+                self.html_block = false;
+                self.out.end_block = true;
+            }
             match event {
                 Start(tag) => {
                     self.start_tag(tag)?;
@@ -131,369 +56,222 @@ where
                     self.end_tag(tag)?;
                 }
                 Text(text) => {
-                    self.write(&text)?;
-                    self.end_newline = text.ends_with('\n');
+                    self.out.write(&text)?;
                 }
                 Code(text) => {
-                    self.write("<code>")?;
-                    self.write(&text)?;
-                    self.write("</code>")?;
+                    let mut fence = String::from("`");
+                    while text.contains(fence.as_str()) {
+                        fence.push('`');
+                    }
+                    if text.starts_with('`') || text.ends_with('`') {
+                        self.out.write(&format!("{} {} {}", fence, text, fence))?;
+                    } else {
+                        self.out.write(&format!("{}{}{}", fence, text, fence))?;
+                    }
                 }
                 Html(html) => {
-                    self.write(&html)?;
+                    if html.ends_with('\n') {
+                        if !self.html_block {
+                            self.out.start_block()?;
+                        }
+                        self.html_block = true;
+                    }
+                    self.out.write(&html)?;
                 }
                 SoftBreak => {
-                    self.write_newline()?;
+                    self.out.write_newline()?;
                 }
                 HardBreak => {
-                    self.write("<br />\n")?;
+                    self.out.write("\\")?;
+                    self.out.write_newline()?;
                 }
                 Rule => {
-                    if self.end_newline {
-                        self.write("<hr />\n")?;
-                    } else {
-                        self.write("\n<hr />\n")?;
-                    }
+                    self.out.start_block()?;
+                    self.out.write("---")?;
+                    self.out.end_block()?;
                 }
                 FootnoteReference(name) => {
-                    let len = self.numbers.len() + 1;
-                    self.write("<sup class=\"footnote-reference\"><a href=\"#")?;
-                    // escape_html(&mut self.writer, &name)?;
-                    self.write("\">")?;
-                    let number = *self.numbers.entry(name).or_insert(len);
-                    write!(&mut self.writer, "{}", number)?;
-                    self.write("</a></sup>")?;
+                    self.out.write(&format!("[^{}]", name))?;
                 }
                 TaskListMarker(true) => {
-                    self.write("<input disabled=\"\" type=\"checkbox\" checked=\"\"/>\n")?;
+                    self.out.write("[x] ")?;
                 }
                 TaskListMarker(false) => {
-                    self.write("<input disabled=\"\" type=\"checkbox\"/>\n")?;
+                    self.out.write("[ ] ")?;
                 }
             }
         }
         Ok(())
     }
 
-    /// Writes the start of an HTML tag.
+    /// Writes the start of a Markdown element.
     fn start_tag(&mut self, tag: Tag<'a>) -> io::Result<()> {
         match tag {
-            Tag::Paragraph => {
-                // self.write("\n");
-                self.write("\n")
-            }
-            Tag::Heading(level) => {
-                if level > 1 {
-                    self.write("\n")?;
-                }
+            Tag::Paragraph => self.out.start_block(),
+            Tag::Heading(level, _, _) => {
+                self.out.start_block()?;
                 let heading = "#".repeat(level as usize);
-                self.write(&format!("{} ", heading))
+                self.out.write(&format!("{} ", heading))
             }
             Tag::Table(alignments) => {
                 self.table_alignments = alignments;
-                self.write("<table>")
-            }
-            Tag::TableHead => {
-                self.table_state = TableState::Head;
-                self.table_cell_index = 0;
-                self.write("<thead><tr>")
-            }
-            Tag::TableRow => {
-                self.table_cell_index = 0;
-                self.write("<tr>")
-            }
-            Tag::TableCell => {
-                match self.table_state {
-                    TableState::Head => {
-                        self.write("<th")?;
-                    }
-                    TableState::Body => {
-                        self.write("<td")?;
-                    }
-                }
-                match self.table_alignments.get(self.table_cell_index) {
-                    Some(&Alignment::Left) => self.write(" align=\"left\">"),
-                    Some(&Alignment::Center) => self.write(" align=\"center\">"),
-                    Some(&Alignment::Right) => self.write(" align=\"right\">"),
-                    _ => self.write(">"),
-                }
+                self.out.start_block()
             }
+            Tag::TableHead | Tag::TableRow => self.out.write("|"),
+            Tag::TableCell => self.out.write(" "),
             Tag::BlockQuote => {
-                if self.end_newline {
-                    self.write("<blockquote>\n")
-                } else {
-                    self.write("\n<blockquote>\n")
-                }
+                self.out.start_block()?;
+                self.out.push_container("> ");
+                Ok(())
             }
             Tag::CodeBlock(info) => {
-                if !self.end_newline {
-                    self.write_newline()?;
-                }
+                self.out.start_block()?;
                 match info {
-                    CodeBlockKind::Fenced(info) => {
-                        let lang = info.split(' ').next().unwrap();
-                        if lang.is_empty() {
-                            self.write("<pre><code>")
-                        } else {
-                            self.write("<pre><code class=\"language-")?;
-                            // escape_html(&mut self.writer, lang)?;
-                            self.write("\">")
-                        }
-                    }
-                    CodeBlockKind::Indented => self.write("<pre><code>"),
-                }
-            }
-            Tag::List(Some(1)) => {
-                if self.end_newline {
-                    self.write("<ol>\n")
-                } else {
-                    self.write("\n<ol>\n")
+                    CodeBlockKind::Fenced(info) => self.out.write(&format!("```{}\n", info)),
+                    CodeBlockKind::Indented => self.out.write("```\n"),
                 }
             }
-            Tag::List(Some(start)) => {
-                if self.end_newline {
-                    self.write("<ol start=\"")?;
-                } else {
-                    self.write("\n<ol start=\"")?;
-                }
-                write!(&mut self.writer, "{}", start)?;
-                self.write("\">\n")
-            }
-            Tag::List(None) => {
-                // self.write("ST LIST")?;
-                self.write("\n")
-
-                // self.write("<start list>")
-
-                // if self.end_newline {
-                //
-                // } else {
-                //     self.write("\n<ul>\n")
-                // }
+            Tag::List(start) => {
+                self.out.start_block()?;
+                self.lists.push(start);
+                Ok(())
             }
             Tag::Item => {
-                // if self.end_newline {
-                //     self.write("- ")
-                // } else {
-                self.write("- ")
-                // }
-                // Ok(())
-            }
-            Tag::Emphasis => self.write("<em>"),
-            Tag::Strong => self.write("<strong>"),
-            Tag::Strikethrough => self.write("<del>"),
-            Tag::Link(LinkType::Email, dest, title) => {
-                self.write("<a href=\"mailto:")?;
-                // escape_href(&mut self.writer, &dest)?;
-                if !title.is_empty() {
-                    self.write("\" title=\"")?;
-                    // escape_html(&mut self.writer, &title)?;
+                if !self.out.end_newline {
+                    self.out.write_newline()?;
                 }
-                self.write("\">")
-            }
-            Tag::Link(_link_type, dest, title) => {
-                let next = self.iter.next();
-                if let Some(Event::Text(tag)) = next {
-                    self.write(&format!("[{}]({}", tag, dest))?
+                if self.out.end_block {
+                    self.out.write_newline()?;
+                    self.out.end_block = false;
                 }
-                // self.write("ST LINK")?;
-                return Ok(());
-            }
-            Tag::Image(_link_type, dest, title) => {
-                self.write("<img src=\"")?;
-                // escape_href(&mut self.writer, &dest)?;
-                self.write("\" alt=\"")?;
-                self.raw_text()?;
-                if !title.is_empty() {
-                    self.write("\" title=\"")?;
-                    // escape_html(&mut self.writer, &title)?;
-                }
-                self.write("\" />")
-            }
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => String::from("- "),
+                };
+                self.out.write(&marker)?;
+                self.out.push_container(&" ".repeat(marker.len()));
+                self.out.after_marker = true;
+                Ok(())
+            }
+            Tag::Emphasis => self.out.write("*"),
+            Tag::Strong => self.out.write("**"),
+            Tag::Strikethrough => self.out.write("~~"),
+            Tag::Link(LinkType::Autolink, _, _) | Tag::Link(LinkType::Email, _, _) => {
+                self.out.write("<")
+            }
+            Tag::Link(_, _, _) => self.out.write("["),
+            Tag::Image(_, _, _) => self.out.write("!["),
             Tag::FootnoteDefinition(name) => {
-                if self.end_newline {
-                    self.write("<div class=\"footnote-definition\" id=\"")?;
-                } else {
-                    self.write("\n<div class=\"footnote-definition\" id=\"")?;
-                }
-                // escape_html(&mut self.writer, &*name)?;
-                self.write("\"><sup class=\"footnote-definition-label\">")?;
-                let len = self.numbers.len() + 1;
-                let number = *self.numbers.entry(name).or_insert(len);
-                write!(&mut self.writer, "{}", number)?;
-                self.write("</sup>")
+                self.out.start_block()?;
+                self.out.write(&format!("[^{}]: ", name))?;
+                self.out.push_container("    ");
+                self.out.after_marker = true;
+                Ok(())
             }
         }
     }
 
     fn end_tag(&mut self, tag: Tag) -> io::Result<()> {
         match tag {
-            Tag::Paragraph => {
-                self.write("\n")?;
-            }
-            Tag::Heading(_) => {
-                self.write("\n")?;
+            Tag::Heading(..) => {
+                // a closing `#` would be taken for the closing sequence
+                if self.out.last_char == Some('#') {
+                    self.out.write(" #")?;
+                }
+                self.out.end_block()?;
             }
-            Tag::Table(_) => {
-                self.write("</tbody></table>\n")?;
+            Tag::Paragraph | Tag::Table(_) => {
+                self.out.end_block()?;
             }
             Tag::TableHead => {
-                self.write("</tr></thead><tbody>\n")?;
-                self.table_state = TableState::Body;
+                self.out.write_newline()?;
+                let separators: Vec<&str> = self
+                    .table_alignments
+                    .iter()
+                    .map(|alignment| match alignment {
+                        Alignment::Left => " :--- |",
+                        Alignment::Center => " :---: |",
+                        Alignment::Right => " ---: |",
+                        Alignment::None => " --- |",
+                    })
+                    .collect();
+                self.out.write(&format!("|{}", separators.concat()))?;
+                self.out.write_newline()?;
             }
             Tag::TableRow => {
-                self.write("</tr>\n")?;
+                self.out.write_newline()?;
             }
             Tag::TableCell => {
-                match self.table_state {
-                    TableState::Head => {
-                        self.write("</th>")?;
-                    }
-                    TableState::Body => {
-                        self.write("</td>")?;
-                    }
-                }
-                self.table_cell_index += 1;
+                self.out.write(" |")?;
             }
             Tag::BlockQuote => {
-                self.write("</blockquote>\n")?;
+                self.out.pop_container();
+                self.out.end_block = true;
             }
             Tag::CodeBlock(_) => {
-                self.write("</code></pre>\n")?;
-            }
-            Tag::List(Some(_)) => {
-                // self.write("</ol>\n")?;
+                if !self.out.end_newline {
+                    self.out.write_newline()?;
+                }
+                self.out.write("```")?;
+                self.out.end_block()?;
             }
-            Tag::List(None) => {
-                // println!("end list");
-                // self.write("\n")?;
+            Tag::List(_) => {
+                self.lists.pop();
+                if !self.out.end_newline {
+                    self.out.write_newline()?;
+                }
+                // a nested list does not make its parent loose
+                self.out.end_block = self.lists.is_empty();
             }
             Tag::Item => {
-                self.write("\n")?;
+                self.out.pop_container();
+                self.out.after_marker = false;
+                if !self.out.end_newline {
+                    self.out.write_newline()?;
+                }
             }
             Tag::Emphasis => {
-                self.write("</em>")?;
+                self.out.write("*")?;
             }
             Tag::Strong => {
-                self.write("</strong>")?;
+                self.out.write("**")?;
             }
             Tag::Strikethrough => {
-                self.write("</del>")?;
+                self.out.write("~~")?;
             }
-            Tag::Link(_, _, _) => {
-                self.write(")")?;
+            Tag::Link(LinkType::Autolink, _, _) | Tag::Link(LinkType::Email, _, _) => {
+                self.out.write(">")?;
             }
-            Tag::Image(_, _, _) => (), // shouldn't happen, handled in start
-            Tag::FootnoteDefinition(_) => {
-                self.write("</div>\n")?;
-            }
-        }
-        Ok(())
-    }
-
-    // run raw text, consuming end tag
-    fn raw_text(&mut self) -> io::Result<()> {
-        let mut nest = 0;
-        while let Some(event) = self.iter.next() {
-            match event {
-                Start(_) => nest += 1,
-                End(_) => {
-                    if nest == 0 {
-                        break;
-                    }
-                    nest -= 1;
-                }
-                Html(text) | Code(text) | Text(text) => {
-                    // escape_html(&mut self.writer, &text)?;
-                    self.end_newline = text.ends_with('\n');
-                }
-                SoftBreak | HardBreak | Rule => {
-                    self.write(" ")?;
-                }
-                FootnoteReference(name) => {
-                    let len = self.numbers.len() + 1;
-                    let number = *self.numbers.entry(name).or_insert(len);
-                    write!(&mut self.writer, "[{}]", number)?;
+            Tag::Link(_, dest, title) | Tag::Image(_, dest, title) => {
+                if title.is_empty() {
+                    self.out.write(&format!("]({})", dest))?;
+                } else {
+                    self.out.write(&format!("]({} \"{}\")", dest, title))?;
                 }
-                TaskListMarker(true) => self.write("[x]")?,
-                TaskListMarker(false) => self.write("[ ]")?,
+            }
+            Tag::FootnoteDefinition(_) => {
+                self.out.pop_container();
+                self.out.end_block()?;
             }
         }
         Ok(())
     }
 }
 
-/// Iterate over an `Iterator` of `Event`s, generate HTML for each `Event`, and
-/// push it to a `String`.
-///
-/// # Examples
-///
-/// ```
-/// use pulldown_cmark::{html, Parser};
-///
-/// let markdown_str = r#"
-/// hello
-/// =====
-///
-/// * alpha
-/// * beta
-/// "#;
-/// let parser = Parser::new(markdown_str);
-///
-/// let mut html_buf = String::new();
-/// html::push_html(&mut html_buf, parser);
-///
-/// assert_eq!(html_buf, r#"<h1>hello</h1>
-/// <ul>
-/// <li>alpha</li>
-/// <li>beta</li>
-/// </ul>
-/// "#);
-/// ```
-pub fn push_html<'a, I>(s: &mut String, iter: I)
-where
-    I: Iterator<Item = Event<'a>>,
-{
-    HtmlWriter::new(iter, s).run().unwrap();
-}
-
-/// Iterate over an `Iterator` of `Event`s, generate HTML for each `Event`, and
-/// write it out to a writable stream.
+/// Iterate over an `Iterator` of `Event`s, generate Markdown for each
+/// `Event`, and write it out to a writable stream.
 ///
 /// **Note**: using this function with an unbuffered writer like a file or socket
 /// will result in poor performance. Wrap these in a
 /// [`BufWriter`](https://doc.rust-lang.org/std/io/struct.BufWriter.html) to
 /// prevent unnecessary slowdowns.
-///
-/// # Examples
-///
-/// ```
-/// use pulldown_cmark::{html, Parser};
-/// use std::io::Cursor;
-///
-/// let markdown_str = r#"
-/// hello
-/// =====
-///
-/// * alpha
-/// * beta
-/// "#;
-/// let mut bytes = Vec::new();
-/// let parser = Parser::new(markdown_str);
-///
-/// html::write_html(Cursor::new(&mut bytes), parser);
-///
-/// assert_eq!(&String::from_utf8_lossy(&bytes)[..], r#"<h1>hello</h1>
-/// <ul>
-/// <li>alpha</li>
-/// <li>beta</li>
-/// </ul>
-/// "#);
-/// ```
-pub fn write<'a, I, W>(writer: W, iter: I) -> io::Result<()>
+pub(crate) fn write<'a, I, W>(writer: W, iter: I) -> io::Result<()>
 where
     I: Iterator<Item = Event<'a>>,
     W: Write,
 {
-    HtmlWriter::new(iter, WriteWrapper(writer)).run()
+    MarkdownWriter::new(iter, WriteWrapper(writer)).run()
 }
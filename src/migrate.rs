@@ -0,0 +1,483 @@
+//! Migration of changelogs written in other styles to Keep a Changelog.
+//!
+//! Releases are recognized by a version in any heading, e.g.
+//! `# v1.2.3 (2019-02-01)` or a setext `Version 1.2 - Feb 2019`, their dates
+//! in the common formats, and entries are classified by the section they are
+//! in or by a keyword they start with, like `Fix:` or `[Added]`. What could
+//! not be recognized is reported along with the line it is on.
+
+use std::ops::Range;
+
+use regex::{Captures, Regex};
+
+use crate::changelog::{Changelog, Entry, HeadingStyle, Kind, Release, Section};
+use crate::date::Date;
+use crate::error::ChangelogError;
+use crate::locale::Locale;
+use crate::parse::{self, Block, BlockKind};
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// Words telling the kind of a section or an entry, in English.
+const KEYWORDS: [(Kind, &[&str]); 6] = [
+    (
+        Kind::Added,
+        &["add", "added", "adds", "new", "feature", "features", "feat"],
+    ),
+    (
+        Kind::Changed,
+        &[
+            "change",
+            "changed",
+            "changes",
+            "update",
+            "updated",
+            "improve",
+            "improved",
+            "improvement",
+            "improvements",
+            "enhancement",
+            "enhancements",
+            "refactor",
+            "perf",
+            "breaking",
+            "docs",
+            "chore",
+            "build",
+            "ci",
+            "test",
+            "tests",
+            "style",
+            "revert",
+        ],
+    ),
+    (
+        Kind::Deprecated,
+        &[
+            "deprecate",
+            "deprecated",
+            "deprecates",
+            "deprecation",
+            "deprecations",
+        ],
+    ),
+    (
+        Kind::Removed,
+        &[
+            "remove", "removed", "removes", "removal", "removals", "delete", "deleted", "drop",
+            "dropped",
+        ],
+    ),
+    (
+        Kind::Fixed,
+        &[
+            "fix", "fixed", "fixes", "bugfix", "bugfixes", "bug", "bugs", "hotfix",
+        ],
+    ),
+    (
+        Kind::Security,
+        &["security", "sec", "cve", "vulnerability", "vulnerabilities"],
+    ),
+];
+
+/// Kind of a keyword, or of a heading of `locale`.
+fn keyword_kind(word: &str, locale: &Locale) -> Option<Kind> {
+    let word = word.to_lowercase();
+    KEYWORDS
+        .iter()
+        .find(|(_, words)| words.contains(&word.as_str()))
+        .map(|(kind, _)| *kind)
+        .or_else(|| locale.kind(&word))
+}
+
+/// Kind of a section heading, from the heading itself or its first word
+/// that is a keyword, as in `Bug fixes`.
+fn section_kind(title: &str, locale: &Locale) -> Option<Kind> {
+    locale.kind(title).or_else(|| {
+        title
+            .split(|c: char| !c.is_alphanumeric())
+            .find_map(|word| keyword_kind(word, locale))
+    })
+}
+
+/// Kind of an entry from a keyword it starts with, and the entry without
+/// it when it is set apart, as in `Fix: ...`, `[Fixed] ...`, `fix - ...` or
+/// the Conventional Commits `feat(cli)!: ...`.
+/// A verb like `Fixed` in `Fixed a crash` is kept.
+fn entry_kind(text: &str, locale: &Locale) -> Option<(Kind, String)> {
+    lazy_static! {
+        static ref PREFIX: Regex = Regex::new(
+            r"^(?:\[(?P<bracket>[^\]\n]+)\]|\*\*(?P<bold>[^*\n]+?):?\*\*:?|(?P<word>[\p{L}-]+)(?:\([^)\n]*\))?!?(?: *:| +-))[ \t]+"
+        )
+        .unwrap();
+        static ref WORD: Regex = Regex::new(r"^\p{L}+").unwrap();
+    }
+
+    if let Some(captures) = PREFIX.captures(text) {
+        let prefix = ["bracket", "bold", "word"]
+            .iter()
+            .find_map(|name| captures.name(name))
+            .map_or("", |m| m.as_str());
+        if let Some(kind) = keyword_kind(prefix.trim(), locale) {
+            let rest = &text[captures.get(0).map_or(0, |m| m.end())..];
+            // an entry starts with a capital, as most in a changelog do
+            let mut chars = rest.chars();
+            let rest = match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            };
+            return Some((kind, rest));
+        }
+    }
+    WORD.find(text)
+        .and_then(|word| keyword_kind(word.as_str(), locale))
+        .map(|kind| (kind, text.to_string()))
+}
+
+/// Date in some text, with its place in it and whether its day was
+/// missing and taken as the first of the month.
+fn find_date(text: &str) -> Option<(Range<usize>, Date, bool)> {
+    lazy_static! {
+        static ref ISO: Regex =
+            Regex::new(r"\b(?P<y>\d{4})[-/.](?P<m>\d{1,2})[-/.](?P<d>\d{1,2})\b").unwrap();
+        static ref NUMERIC: Regex =
+            Regex::new(r"\b(?P<a>\d{1,2})(?P<sep>[./-])(?P<b>\d{1,2})[./-](?P<y>\d{4})\b").unwrap();
+        static ref MONTH_FIRST: Regex = Regex::new(
+            r"(?i)\b(?P<month>[a-z]{3,9})\.? +(?P<d>\d{1,2})(?:st|nd|rd|th)?,? +(?P<y>\d{4})\b"
+        )
+        .unwrap();
+        static ref DAY_FIRST: Regex = Regex::new(
+            r"(?i)\b(?P<d>\d{1,2})(?:st|nd|rd|th)?\.? +(?P<month>[a-z]{3,9})\.?,? +(?P<y>\d{4})\b"
+        )
+        .unwrap();
+        static ref MONTH_YEAR: Regex =
+            Regex::new(r"(?i)\b(?P<month>[a-z]{3,9})\.?,? +(?P<y>\d{4})\b").unwrap();
+    }
+
+    let month = |captures: &Captures| -> Option<u32> {
+        let name = captures["month"].to_lowercase();
+        let position = MONTHS.iter().position(|m| {
+            name.len() >= 3 && m.starts_with(&name) || name == "sept" && *m == "september"
+        })?;
+        Some(position as u32 + 1)
+    };
+    let date = |y: &str, m: u32, d: &str| -> Option<Date> {
+        format!("{}-{:02}-{:0>2}", y, m, d).parse().ok()
+    };
+
+    if let Some(c) = ISO.captures(text) {
+        let found = date(&c["y"], c["m"].parse().ok()?, &c["d"]);
+        return found.map(|d| (c.get(0).unwrap().range(), d, false));
+    }
+    if let Some(c) = NUMERIC.captures(text) {
+        let (a, b): (u32, u32) = (c["a"].parse().ok()?, c["b"].parse().ok()?);
+        // day first, as in Europe, unless it cannot be or it is written
+        // with slashes, as in the US
+        let (m, d) = if a > 12 || (b <= 12 && &c["sep"] != "/") {
+            (b, a)
+        } else {
+            (a, b)
+        };
+        let found = date(&c["y"], m, &d.to_string());
+        return found.map(|date| (c.get(0).unwrap().range(), date, false));
+    }
+    for pattern in [&*MONTH_FIRST, &*DAY_FIRST] {
+        for c in pattern.captures_iter(text) {
+            if let Some(found) = month(&c).and_then(|m| date(&c["y"], m, &c["d"])) {
+                return Some((c.get(0).unwrap().range(), found, false));
+            }
+        }
+    }
+    for c in MONTH_YEAR.captures_iter(text) {
+        if let Some(found) = month(&c).and_then(|m| date(&c["y"], m, "01")) {
+            return Some((c.get(0).unwrap().range(), found, true));
+        }
+    }
+    None
+}
+
+/// Release of a heading with a version, or the label of upcoming changes.
+fn find_release(heading: &str, locale: &Locale) -> Option<(Release, Option<String>)> {
+    lazy_static! {
+        static ref VERSION: Regex = Regex::new(
+            r"(?i)(?:^|[^\w.])v?(?P<version>\d+(?:\.\d+)+(?:-[0-9a-z.-]+)?(?:\+[0-9a-z.-]+)?)\b"
+        )
+        .unwrap();
+        static ref UPCOMING: Regex =
+            Regex::new(r"(?i)\b(unreleased|upcoming|unpublished|next release|next version)\b")
+                .unwrap();
+        static ref YANKED: Regex = Regex::new(r"(?i)\byanked\b").unwrap();
+    }
+
+    let yanked = YANKED.is_match(heading);
+    let (rest, date, note) = match find_date(heading) {
+        Some((range, date, guessed)) => {
+            let rest = format!("{} {}", &heading[..range.start], &heading[range.end..]);
+            let note = if guessed {
+                Some(format!(
+                    "'{}' has no day, the first of the month is assumed",
+                    heading
+                ))
+            } else {
+                None
+            };
+            (rest, Some(date.to_string()), note)
+        }
+        None => (heading.to_string(), None, None),
+    };
+
+    let release = |version: String, unreleased: bool, date: Option<String>| Release {
+        version,
+        date,
+        unreleased,
+        yanked,
+        description: String::new(),
+        sections: vec![],
+        style: HeadingStyle::default(),
+    };
+    if UPCOMING.is_match(&rest) || locale.is_unreleased(&rest) {
+        return Some((release(locale.unreleased.to_string(), true, None), None));
+    }
+    VERSION
+        .captures(&rest)
+        .map(|captures| (release(captures["version"].to_string(), false, date), note))
+}
+
+/// Sorts the blocks of a release into its sections.
+#[derive(Default)]
+pub(crate) struct Sorter {
+    /// Kind of the section the blocks are in, if it is known.
+    kind: Option<Kind>,
+
+    /// Whether the blocks are under a section heading.
+    in_section: bool,
+
+    /// Whether the section is not about changes, like the contributors of
+    /// the release, and is kept in the release description.
+    aside: bool,
+}
+
+impl Sorter {
+    /// Adds a block of `input` to `release`: a heading starts a section,
+    /// the items of a list are classified and other blocks are kept as the
+    /// description of their section.
+    pub(crate) fn push(
+        &mut self,
+        release: &mut Release,
+        input: &str,
+        block: &Block,
+        locale: &Locale,
+        notes: &mut Vec<String>,
+    ) {
+        lazy_static! {
+            // headings of sections that are not about changes
+            static ref ASIDE: Regex =
+                Regex::new(r"(?i)\b(contributors|contributions|thanks|credits|acknowledge?ments)\b").unwrap();
+        }
+
+        let line = parse::line_of(input, block.range.start);
+        match block.kind {
+            BlockKind::Heading(_) => {
+                let title = parse::heading_text(&input[block.range.clone()]);
+                self.aside = ASIDE.is_match(&title);
+                self.kind = section_kind(&title, locale).filter(|_| !self.aside);
+                self.in_section = true;
+                if self.aside {
+                    let heading = format!("**{}**", title.trim_end_matches(':'));
+                    parse::append(
+                        &mut release.description,
+                        &heading,
+                        &(0..heading.len()),
+                        None,
+                    );
+                } else if self.kind.is_none() {
+                    notes.push(format!(
+                        "line {}: section '{}' is not a kind of change, its entries are classified one by one",
+                        line, title
+                    ));
+                }
+            }
+            BlockKind::List if !self.aside => {
+                for item in &block.items {
+                    let item_line = parse::line_of(input, item.start);
+                    let text = parse::item_text(&input[item.clone()]);
+                    let (entry_kind, text) = match entry_kind(&text, locale) {
+                        Some((prefix_kind, rest)) => (prefix_kind, rest),
+                        None => match self.kind {
+                            Some(kind) => (kind, text),
+                            None => {
+                                notes.push(format!(
+                                    "line {}: '{}' could not be classified, it is in {}",
+                                    item_line,
+                                    text.lines().next().unwrap_or(""),
+                                    locale.heading(Kind::Changed)
+                                ));
+                                (Kind::Changed, text)
+                            }
+                        },
+                    };
+                    if text.trim().is_empty() || !parse::is_item(&text) {
+                        notes.push(format!(
+                            "line {}: an empty or malformed entry is left out",
+                            item_line
+                        ));
+                        continue;
+                    }
+                    release.push_entry(entry_kind, Entry::new(&text), locale);
+                }
+            }
+            _ => {
+                // text of a section goes with the entries of its kind
+                let target = match (self.in_section, self.kind) {
+                    (true, Some(kind)) => {
+                        if release.section(kind).is_none() {
+                            release.sections.push(Section::new(kind, locale));
+                            release.sections.sort_by_key(|s| s.kind);
+                        }
+                        release
+                            .sections
+                            .iter_mut()
+                            .find(|s| s.kind == Some(kind))
+                            .map(|s| &mut s.description)
+                    }
+                    _ => Some(&mut release.description),
+                };
+                if let Some(target) = target {
+                    parse::append(target, input, &block.range, None);
+                }
+                if self.in_section && self.kind.is_none() && !self.aside {
+                    notes.push(format!(
+                        "line {}: text of an unknown section is in the release description",
+                        line
+                    ));
+                }
+            }
+        }
+    }
+}
+
+impl Changelog {
+    /// Reads a changelog written in another style, with notes on what could
+    /// not be recognized, each starting with its line.
+    ///
+    /// Releases are written newest first, with the standard preamble; the
+    /// text before the first release is not kept.
+    pub fn migrate(
+        input: &str,
+        locale: &'static Locale,
+    ) -> Result<(Changelog, Vec<String>), ChangelogError> {
+        let input = &input.replace("\r\n", "\n").replace('\r', "\n");
+        let blocks = parse::blocks(input);
+        let mut notes = vec![];
+
+        let mut changelog = Changelog::new(locale);
+        changelog.releases.clear();
+        let mut sorter = Sorter::default();
+        let mut gaps = vec![];
+        let mut start = 0;
+        let mut dropped = false;
+
+        for block in &blocks {
+            let raw = &input[block.range.clone()];
+            let line = parse::line_of(input, block.range.start);
+            gaps.push(start..block.range.start.max(start));
+            start = start.max(block.range.end);
+
+            let heading = match block.kind {
+                BlockKind::Heading(l) => Some((l, parse::heading_text(raw))),
+                _ => None,
+            };
+            // releases are the headings with a version, at any level
+            if let Some(release) = heading
+                .as_ref()
+                .and_then(|(_, text)| find_release(text, locale))
+            {
+                let (release, note) = release;
+                if let Some(note) = note {
+                    notes.push(format!("line {}: {}", line, note));
+                }
+                if release.unreleased && changelog.releases.iter().any(|r| r.unreleased) {
+                    notes.push(format!(
+                        "line {}: a second Unreleased heading is merged",
+                        line
+                    ));
+                }
+                changelog.releases.push(release);
+                sorter = Sorter::default();
+                continue;
+            }
+
+            // the title is replaced by the standard one
+            let release = match changelog.releases.last_mut() {
+                Some(release) => release,
+                None => {
+                    if !dropped && heading.is_none() {
+                        dropped = true;
+                        notes.push(format!(
+                            "line {}: text before the first release is left out",
+                            line
+                        ));
+                    }
+                    continue;
+                }
+            };
+
+            sorter.push(release, input, block, locale, &mut notes);
+        }
+        gaps.push(start..input.len());
+        if changelog.releases.is_empty() {
+            return Err(ChangelogError::Parse {
+                line: 1,
+                message: String::from("no heading with a version was found"),
+            });
+        }
+
+        // merge a repeated Unreleased, and keep it first
+        let mut unreleased: Option<Release> = None;
+        let mut releases = vec![];
+        for release in changelog.releases.drain(..) {
+            match (&mut unreleased, release.unreleased) {
+                (Some(first), true) => {
+                    for section in release.sections {
+                        for entry in section.entries {
+                            if let Some(kind) = section.kind {
+                                first.push_entry(kind, entry.clone(), locale);
+                            }
+                        }
+                    }
+                }
+                (None, true) => unreleased = Some(release),
+                _ => releases.push(release),
+            }
+        }
+
+        // newest first, as some changelogs are the other way round
+        let dates: Vec<&str> = releases.iter().filter_map(|r| r.date.as_deref()).collect();
+        if dates.len() > 1 && dates.first() < dates.last() {
+            releases.reverse();
+            notes.push(String::from(
+                "releases were oldest first, they are now newest first",
+            ));
+        }
+
+        changelog.releases = unreleased.into_iter().chain(releases).collect();
+        if !changelog.releases.first().is_some_and(|r| r.unreleased) {
+            changelog.releases.insert(0, Release::unreleased(locale));
+        }
+        changelog.links = parse::links(input, &gaps);
+        Ok((changelog, notes))
+    }
+}
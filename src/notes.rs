@@ -0,0 +1,156 @@
+//! Release notes, rendered with [TinyTemplate](https://docs.rs/tinytemplate)
+//! from a template of the user or a built-in one.
+//!
+//! Values are written as they are, not escaped for HTML. A template is
+//! given:
+//!
+//! - `project`: name of the project, as given
+//! - `version`, `date` (if any), `unreleased`, `yanked`
+//! - `title`: version, followed by `[YANKED]` if it was
+//! - `url`: link of the release's heading, usually comparing it with the
+//!   previous one
+//! - `previous`: version of the previous release, if any
+//! - `description`: Markdown of the description
+//! - `sections`: each with `title`, `kind` (if standard), `description` as
+//!   Markdown and `entries`, each with `id`, `markdown` and `text`
+//! - `links`: links of the release, each with `text` and `url`
+
+use pulldown_cmark::{Event, Tag};
+use serde::Serialize;
+use tinytemplate::TinyTemplate;
+
+use crate::changelog::{Changelog, Release};
+use crate::error::ChangelogError;
+use crate::schema::plain_text;
+
+/// Built-in templates, by name: `markdown`, `slack` and `email`.
+pub const TEMPLATES: [(&str, &str); 3] = [
+    ("markdown", include_str!("../templates/notes/markdown.md")),
+    ("slack", include_str!("../templates/notes/slack.txt")),
+    ("email", include_str!("../templates/notes/email.txt")),
+];
+
+#[derive(Serialize)]
+struct EntryNotes {
+    id: String,
+    markdown: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct SectionNotes<'a> {
+    title: &'a str,
+    kind: Option<&'static str>,
+    description: &'a str,
+    entries: Vec<EntryNotes>,
+}
+
+#[derive(Serialize, PartialEq)]
+struct LinkNotes {
+    text: String,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct Notes<'a> {
+    project: &'a str,
+    version: &'a str,
+    date: Option<&'a str>,
+    unreleased: bool,
+    yanked: bool,
+    title: String,
+    url: Option<&'a str>,
+    previous: Option<&'a str>,
+    description: &'a str,
+    sections: Vec<SectionNotes<'a>>,
+    links: Vec<LinkNotes>,
+}
+
+impl Changelog {
+    /// Notes of `release` of `project` from the text of a template.
+    pub fn release_notes(
+        &self,
+        release: &Release,
+        project: &str,
+        template: &str,
+    ) -> Result<String, ChangelogError> {
+        let previous = self
+            .releases
+            .iter()
+            .skip_while(|r| !std::ptr::eq(*r, release))
+            .skip(1)
+            .find(|r| !r.unreleased)
+            .map(|r| r.version.as_str());
+
+        let mut links: Vec<LinkNotes> = vec![];
+        let markdown = std::iter::once(&release.description)
+            .chain(release.sections.iter().map(|s| &s.description))
+            .chain(
+                release
+                    .sections
+                    .iter()
+                    .flat_map(|s| s.entries.iter().map(|e| &e.text)),
+            );
+        for markdown in markdown {
+            let mut link: Option<LinkNotes> = None;
+            for event in self.markdown_events(markdown, false) {
+                match (event, &mut link) {
+                    (Event::Start(Tag::Link(_, url, _)), None) => {
+                        link = Some(LinkNotes {
+                            text: String::new(),
+                            url: url.to_string(),
+                        })
+                    }
+                    (Event::Text(text), Some(link)) | (Event::Code(text), Some(link)) => {
+                        link.text.push_str(&text)
+                    }
+                    (Event::End(Tag::Link(..)), Some(_)) => {
+                        if let Some(link) = link.take().filter(|l| !links.contains(l)) {
+                            links.push(link);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        let notes = Notes {
+            project,
+            version: &release.version,
+            date: release.date.as_deref(),
+            unreleased: release.unreleased,
+            yanked: release.yanked,
+            title: release.title(),
+            url: self.release_url(release),
+            previous,
+            description: &release.description,
+            sections: release
+                .sections
+                .iter()
+                .map(|section| SectionNotes {
+                    title: &section.title,
+                    kind: section.kind.map(|kind| kind.name()),
+                    description: &section.description,
+                    entries: section
+                        .entries
+                        .iter()
+                        .map(|entry| EntryNotes {
+                            id: entry.id(),
+                            markdown: entry.text.clone(),
+                            text: plain_text(&entry.text),
+                        })
+                        .collect(),
+                })
+                .collect(),
+            links,
+        };
+
+        let error = |err: tinytemplate::error::Error| {
+            ChangelogError::Validation(format!("template: {}", err))
+        };
+        let mut tt = TinyTemplate::new();
+        tt.set_default_formatter(&tinytemplate::format_unescaped);
+        tt.add_template("notes", template).map_err(error)?;
+        tt.render("notes", &notes).map_err(error)
+    }
+}
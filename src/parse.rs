@@ -0,0 +1,605 @@
+//! Parsing of a Markdown changelog into the document model.
+
+use std::ops::Range;
+
+use pulldown_cmark::{Event, HeadingLevel, Options, Parser, Tag};
+use regex::Regex;
+
+use crate::changelog::{Changelog, DateStyle, Entry, HeadingStyle, Release, Section, VersionStyle};
+use crate::error::ChangelogError;
+use crate::locale::Locale;
+
+/// Whitespace as Markdown sees it: other characters, like a form feed, can
+/// be the text of a line.
+const BLANK: &[char] = &[' ', '\t', '\n'];
+
+#[derive(PartialEq)]
+pub(crate) enum BlockKind {
+    Heading(HeadingLevel),
+    List,
+    Html,
+    Other,
+}
+
+/// A top-level block of the document.
+pub(crate) struct Block {
+    pub(crate) kind: BlockKind,
+    pub(crate) range: Range<usize>,
+
+    /// Source of each item, when the block is a list.
+    pub(crate) items: Vec<Range<usize>>,
+}
+
+/// Splits the document into its top-level blocks.
+pub(crate) fn blocks(input: &str) -> Vec<Block> {
+    let mut blocks: Vec<Block> = vec![];
+    let mut depth = 0;
+
+    for (event, mut range) in Parser::new_ext(input, Options::empty()).into_offset_iter() {
+        if depth == 0 {
+            // blocks start with their indentation
+            let line_start = input[..range.start].rfind('\n').map_or(0, |i| i + 1);
+            if input[line_start..range.start]
+                .trim_matches(&[' ', '\t'][..])
+                .is_empty()
+            {
+                range.start = line_start;
+            }
+        }
+        match event {
+            Event::Start(tag) => {
+                if depth == 0 {
+                    let kind = match tag {
+                        Tag::Heading(level, _, _) => BlockKind::Heading(level),
+                        Tag::List(_) => BlockKind::List,
+                        _ => BlockKind::Other,
+                    };
+                    blocks.push(Block {
+                        kind,
+                        range,
+                        items: vec![],
+                    });
+                } else if depth == 1 && tag == Tag::Item {
+                    if let Some(block) = blocks.last_mut() {
+                        block.items.push(range);
+                    }
+                }
+                depth += 1;
+            }
+            Event::End(_) => depth -= 1,
+            // HTML blocks come as one event per line
+            Event::Html(_) if depth == 0 => match blocks.last_mut() {
+                Some(block) if block.kind == BlockKind::Html && block.range.end == range.start => {
+                    block.range.end = range.end
+                }
+                _ => blocks.push(Block {
+                    kind: BlockKind::Html,
+                    range,
+                    items: vec![],
+                }),
+            },
+            _ if depth == 0 => blocks.push(Block {
+                kind: BlockKind::Other,
+                range,
+                items: vec![],
+            }),
+            _ => (),
+        }
+    }
+    blocks
+}
+
+/// Text of a heading, without its `#` markers or setext underline.
+pub(crate) fn heading_text(raw: &str) -> String {
+    lazy_static! {
+        static ref CLOSING_SEQUENCE: Regex = Regex::new(r"(^|[ \t]+)#+[ \t]*$").unwrap();
+    }
+
+    let raw = raw.trim_matches(BLANK);
+    if raw.starts_with('#') {
+        let text = raw.trim_start_matches('#');
+        CLOSING_SEQUENCE
+            .replace(text, "")
+            .trim_matches(BLANK)
+            .to_string()
+    } else {
+        let lines: Vec<&str> = raw.lines().collect();
+        lines[..lines.len().saturating_sub(1)]
+            .iter()
+            .map(|l| l.trim_matches(BLANK))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Markdown of a list item, without its marker and indentation.
+pub(crate) fn item_text(raw: &str) -> String {
+    lazy_static! {
+        static ref MARKER: Regex = Regex::new(r"^(\s*([-*+]|\d+[.)]))( {1,4})?").unwrap();
+    }
+
+    let mut lines = raw.lines();
+    let first = lines.next().unwrap_or("");
+    let width = MARKER.find(first).map_or(0, |m| m.end());
+    let mut text = first[width..].to_string();
+
+    for line in lines {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        text.push('\n');
+        text.push_str(&line[indent.min(width)..]);
+    }
+    text.trim_end_matches(BLANK).to_string()
+}
+
+/// Whether an entry is written back as a single list item, rather than
+/// ending its list or swallowing what follows, and defines no link, which
+/// would take the place of the document's.
+pub(crate) fn is_item(text: &str) -> bool {
+    let raw = text
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| match (i, line) {
+            (0, _) => format!("- {}", line),
+            (_, "") => String::new(),
+            _ => format!("  {}", line),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    match &blocks(&raw)[..] {
+        [block] => {
+            block.kind == BlockKind::List
+                && block.items.len() == 1
+                && item_text(&raw[block.items[0].clone()]) == text
+                && is_closed(&raw)
+                && Parser::new_ext(&raw, Options::empty())
+                    .reference_definitions()
+                    .iter()
+                    .next()
+                    .is_none()
+        }
+        _ => false,
+    }
+}
+
+/// Reads a release heading: a version, bracketed as in `[1.0.0]`, linked
+/// as in `[1.0.0](https://...)` or plain, then a date if any, after a
+/// hyphen, an en or em dash or in parentheses, and `[YANKED]` if it was.
+fn parse_release(heading: &str, line: usize, locale: &Locale) -> Result<Release, ChangelogError> {
+    lazy_static! {
+        static ref VERSION_PATTERN: Regex = Regex::new(
+            r"^(?:\[(?P<bracketed>[^\]]*)\](?:\((?P<url>[^()\s]*)\))?|(?P<plain>[^\s\[\]()]+))"
+        )
+        .unwrap();
+        static ref YANKED_PATTERN: Regex = Regex::new(r"(?i)(?:^|\s)\[YANKED\]$").unwrap();
+        static ref DATE_PATTERN: Regex = Regex::new(
+            r"^(?:(?P<separator>[-\u{2013}\u{2014}])\s+(?P<date>.*?)|\((?P<parenthesized>[^()]*)\))$"
+        )
+        .unwrap();
+    }
+    let error = |message: String| ChangelogError::Parse {
+        line,
+        message: format!("malformed release heading '{}': {}", heading, message),
+    };
+
+    // a plain Unreleased label can name a line, as in `Unreleased 1.x`
+    if !heading.starts_with('[') && locale.is_unreleased(heading) {
+        return Ok(Release {
+            version: heading.trim().to_string(),
+            date: None,
+            unreleased: true,
+            yanked: false,
+            description: String::new(),
+            sections: vec![],
+            style: HeadingStyle {
+                version: VersionStyle::Plain,
+                date: DateStyle::Hyphen,
+            },
+        });
+    }
+
+    let captures = VERSION_PATTERN.captures(heading).ok_or_else(|| {
+        error(String::from(
+            "expected a version, as in '[1.0.0] - 2020-01-01'",
+        ))
+    })?;
+    let (version, version_style) = match (captures.name("bracketed"), captures.name("url")) {
+        (Some(version), Some(url)) => (
+            version.as_str(),
+            VersionStyle::Linked(url.as_str().to_string()),
+        ),
+        (Some(version), None) => (version.as_str(), VersionStyle::Bracketed),
+        _ => (&captures["plain"], VersionStyle::Plain),
+    };
+    let version = version.trim().to_string();
+    let unreleased = locale.is_unreleased(&version);
+    // anything else is a title, not a release
+    if version_style == VersionStyle::Plain
+        && !unreleased
+        && !version
+            .trim_start_matches(&['v', 'V'][..])
+            .starts_with(|c: char| c.is_ascii_digit())
+    {
+        return Err(error(format!("'{}' is not a version", version)));
+    }
+
+    let mut rest = heading[captures.get(0).map_or(0, |m| m.end())..].trim();
+    let yanked = YANKED_PATTERN.is_match(rest);
+    if let Some(m) = YANKED_PATTERN.find(rest) {
+        rest = rest[..m.start()].trim_end();
+    }
+
+    let (date, date_style) = if rest.is_empty() {
+        (None, DateStyle::Hyphen)
+    } else {
+        let captures = DATE_PATTERN.captures(rest).ok_or_else(|| {
+            error(format!(
+                "version '{}' is followed by '{}', expected ' - DATE', ' (DATE)' or '[YANKED]'",
+                version, rest
+            ))
+        })?;
+        match (captures.name("separator"), captures.name("date")) {
+            (Some(separator), Some(date)) => {
+                let style = match separator.as_str() {
+                    "-" => DateStyle::Hyphen,
+                    "\u{2013}" => DateStyle::EnDash,
+                    _ => DateStyle::EmDash,
+                };
+                (Some(date.as_str()), style)
+            }
+            _ => (
+                captures.name("parenthesized").map(|d| d.as_str()),
+                DateStyle::Parenthesized,
+            ),
+        }
+    };
+    let date = date
+        .map(|date| date.trim().to_string())
+        .filter(|date| !date.is_empty());
+
+    Ok(Release {
+        version,
+        date,
+        unreleased,
+        yanked,
+        description: String::new(),
+        sections: vec![],
+        style: HeadingStyle {
+            version: version_style,
+            date: date_style,
+        },
+    })
+}
+
+/// Appends a block to the Markdown of a part of the document, joined to
+/// the `previous` block of the part as it is in the input.
+pub(crate) fn append(
+    markdown: &mut String,
+    input: &str,
+    block: &Range<usize>,
+    previous: Option<&Range<usize>>,
+) {
+    let raw = input[block.clone()].trim_end_matches(BLANK);
+    if let Some(previous) = previous {
+        let end = previous.start + input[previous.clone()].trim_end_matches(BLANK).len();
+        match input.get(end..block.start) {
+            Some(gap) if gap.contains('\n') => markdown.push_str(gap),
+            _ => markdown.push_str("\n\n"),
+        }
+    } else if !markdown.is_empty() {
+        markdown.push_str("\n\n");
+    }
+    markdown.push_str(raw);
+}
+
+/// Link reference definitions in the parts of the input not kept in any
+/// block or between the blocks of a part.
+///
+/// They are the ones CommonMark finds, except those that would not be
+/// read back the same once written as `[label]: url` after the rest, which
+/// are dropped: with an empty URL, a label on several lines, or a label
+/// the Markdown kept in the document defines too.
+pub(crate) fn links(input: &str, gaps: &[Range<usize>]) -> Vec<(String, String)> {
+    let parser = Parser::new_ext(input, Options::empty());
+
+    // the document without the gaps, whose definitions come first once written
+    let mut kept = input.as_bytes().to_vec();
+    for gap in gaps {
+        kept[gap.clone()].fill(b'\n');
+    }
+    let kept = String::from_utf8(kept).unwrap_or_default();
+    let kept = Parser::new_ext(&kept, Options::empty());
+
+    let mut definitions: Vec<(usize, String, String)> = parser
+        .reference_definitions()
+        .iter()
+        .filter(|(_, definition)| gaps.iter().any(|gap| gap.contains(&definition.span.start)))
+        .filter(|(label, _)| kept.reference_definitions().get(label).is_none())
+        .map(|(label, definition)| {
+            (
+                definition.span.start,
+                label.to_string(),
+                definition.dest.to_string(),
+            )
+        })
+        .filter(|(_, label, url)| is_link_definition(label, url))
+        .collect();
+    definitions.sort();
+    definitions
+        .into_iter()
+        .map(|(_, label, url)| (label, url))
+        .collect()
+}
+
+/// Whether `[label]: url` is read back as the definition of `label` as
+/// `url`.
+fn is_link_definition(label: &str, url: &str) -> bool {
+    let written = format!("[{}]: {}\n", label, url);
+    let parser = Parser::new_ext(&written, Options::empty());
+    let definitions: Vec<_> = parser.reference_definitions().iter().collect();
+    match definitions[..] {
+        [(written_label, definition)] => {
+            written_label == label
+                && definition.dest.as_ref() == url
+                && definition.span.end >= written.trim_end().len()
+        }
+        _ => false,
+    }
+}
+
+/// Whether a block ends where it does, rather than running on into anything
+/// written after it, like an unterminated code block.
+fn is_closed(raw: &str) -> bool {
+    let probe = format!("{}\n\n## probe\n", raw);
+    let mut depth = 0;
+    Parser::new(&probe).any(|event| match event {
+        Event::Start(Tag::Heading(HeadingLevel::H2, _, _)) if depth == 0 => true,
+        Event::Start(_) => {
+            depth += 1;
+            false
+        }
+        Event::End(_) => {
+            depth -= 1;
+            false
+        }
+        _ => false,
+    })
+}
+
+pub(crate) fn line_of(input: &str, offset: usize) -> usize {
+    input[..offset].matches('\n').count() + 1
+}
+
+pub(crate) fn parse(input: &str, locale: &'static Locale) -> Result<Changelog, ChangelogError> {
+    // a lone CR is a line ending too: make them all LF
+    let input = &input.replace("\r\n", "\n").replace('\r', "\n");
+    let blocks = blocks(input);
+
+    // only the last block can run until the end of the input
+    if let Some(block) = blocks.last() {
+        if !is_closed(&input[block.range.clone()]) {
+            return Err(ChangelogError::Parse {
+                line: line_of(input, block.range.start),
+                message: String::from("block is not terminated, e.g. a code block is not closed"),
+            });
+        }
+    }
+
+    let mut preamble = String::new();
+    let mut releases: Vec<Release> = vec![];
+    let mut previous = None;
+    let mut gaps = vec![];
+    let mut start = 0;
+
+    for block in &blocks {
+        let raw = &input[block.range.clone()];
+        let gap = start..block.range.start.max(start);
+        start = start.max(block.range.end);
+
+        let markdown = match (&block.kind, releases.last_mut()) {
+            (BlockKind::Heading(HeadingLevel::H2), _) => {
+                let line = line_of(input, block.range.start);
+                releases.push(parse_release(&heading_text(raw), line, locale)?);
+                None
+            }
+            (_, None) => Some(&mut preamble),
+            (BlockKind::Heading(HeadingLevel::H3), Some(release)) => {
+                let title = heading_text(raw);
+                release.sections.push(Section {
+                    kind: locale.kind(&title),
+                    title,
+                    description: String::new(),
+                    entries: vec![],
+                });
+                None
+            }
+            (BlockKind::List, Some(release)) if !release.sections.is_empty() => {
+                if let Some(section) = release.sections.last_mut() {
+                    section.entries.extend(
+                        block
+                            .items
+                            .iter()
+                            .map(|item| Entry::new(&item_text(&input[item.clone()]))),
+                    );
+                }
+                None
+            }
+            (_, Some(release)) => Some(match release.sections.last_mut() {
+                Some(section) => &mut section.description,
+                None => &mut release.description,
+            }),
+        };
+
+        // what is between two blocks of a part stays with them
+        match markdown {
+            Some(markdown) => {
+                if previous.is_none() {
+                    gaps.push(gap);
+                }
+                append(markdown, input, &block.range, previous);
+                previous = Some(&block.range);
+            }
+            None => {
+                gaps.push(gap);
+                previous = None;
+            }
+        }
+    }
+    gaps.push(start..input.len());
+
+    Ok(Changelog {
+        preamble,
+        releases,
+        links: links(input, &gaps),
+        locale,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::changelog::Changelog;
+
+    /// Release headings with what they are read as: version, date, yanked
+    /// and style.
+    fn headings() -> Vec<(
+        &'static str,
+        &'static str,
+        Option<&'static str>,
+        bool,
+        HeadingStyle,
+    )> {
+        let style = |version, date| HeadingStyle { version, date };
+        let linked = || {
+            VersionStyle::Linked(String::from(
+                "https://github.com/o/r/compare/v0.9.0...v1.0.0",
+            ))
+        };
+        vec![
+            (
+                "[1.0.0] - 2020-01-01",
+                "1.0.0",
+                Some("2020-01-01"),
+                false,
+                style(VersionStyle::Bracketed, DateStyle::Hyphen),
+            ),
+            (
+                "[1.0.0]",
+                "1.0.0",
+                None,
+                false,
+                style(VersionStyle::Bracketed, DateStyle::Hyphen),
+            ),
+            (
+                "[1.0.0](https://github.com/o/r/compare/v0.9.0...v1.0.0) - 2020-01-01",
+                "1.0.0",
+                Some("2020-01-01"),
+                false,
+                style(linked(), DateStyle::Hyphen),
+            ),
+            (
+                "[1.0.0](https://github.com/o/r/compare/v0.9.0...v1.0.0) (2020-01-01) [YANKED]",
+                "1.0.0",
+                Some("2020-01-01"),
+                true,
+                style(linked(), DateStyle::Parenthesized),
+            ),
+            (
+                "1.0.0 \u{2013} 2020-01-01",
+                "1.0.0",
+                Some("2020-01-01"),
+                false,
+                style(VersionStyle::Plain, DateStyle::EnDash),
+            ),
+            (
+                "v1.0.0 \u{2014} 2020-01-01",
+                "v1.0.0",
+                Some("2020-01-01"),
+                false,
+                style(VersionStyle::Plain, DateStyle::EmDash),
+            ),
+            (
+                "1.0.0 (2020-01-01)",
+                "1.0.0",
+                Some("2020-01-01"),
+                false,
+                style(VersionStyle::Plain, DateStyle::Parenthesized),
+            ),
+            (
+                "[1.0.0] - 2020-01-01 [YANKED]",
+                "1.0.0",
+                Some("2020-01-01"),
+                true,
+                style(VersionStyle::Bracketed, DateStyle::Hyphen),
+            ),
+            (
+                "[1.0.0] \u{2013} 2020-01-01 [YANKED]",
+                "1.0.0",
+                Some("2020-01-01"),
+                true,
+                style(VersionStyle::Bracketed, DateStyle::EnDash),
+            ),
+            (
+                "[1.0.0] [YANKED]",
+                "1.0.0",
+                None,
+                true,
+                style(VersionStyle::Bracketed, DateStyle::Hyphen),
+            ),
+        ]
+    }
+
+    #[test]
+    fn release_headings() {
+        for (heading, version, date, yanked, style) in headings() {
+            let release = parse_release(heading, 1, Locale::english()).unwrap();
+            assert_eq!(release.version, version, "{}", heading);
+            assert_eq!(release.date.as_deref(), date, "{}", heading);
+            assert_eq!(release.yanked, yanked, "{}", heading);
+            assert!(!release.unreleased, "{}", heading);
+            assert_eq!(release.style, style, "{}", heading);
+            assert_eq!(release.heading(), heading);
+        }
+    }
+
+    #[test]
+    fn release_headings_are_written_back_the_same() {
+        for (heading, ..) in headings() {
+            let input = format!("# Changelog\n\n## {}\n\n### Added\n\n- Feature\n", heading);
+            let changelog = Changelog::parse(&input).unwrap();
+            assert_eq!(changelog.to_string(), input);
+        }
+    }
+
+    #[test]
+    fn unreleased_headings() {
+        for heading in &[
+            "[Unreleased]",
+            "Unreleased",
+            "Unreleased 1.x",
+            "[Unreleased 1.x]",
+        ] {
+            let release = parse_release(heading, 1, Locale::english()).unwrap();
+            assert!(release.unreleased, "{}", heading);
+            assert_eq!(release.version, heading.trim_matches(&['[', ']'][..]));
+            assert_eq!(release.heading(), *heading);
+        }
+    }
+
+    #[test]
+    fn malformed_release_headings() {
+        for heading in &[
+            "Changes",
+            "[1.0.0] 2020-01-01",
+            "[1.0.0] - ",
+            "1.0.0 (2020-01-01",
+            "[1.0.0](https://example.com",
+        ] {
+            match parse_release(heading, 3, Locale::english()) {
+                Err(ChangelogError::Parse { line: 3, .. }) => (),
+                result => panic!("{}: {:?}", heading, result),
+            }
+        }
+    }
+}
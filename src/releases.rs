@@ -0,0 +1,271 @@
+//! Import of the releases of a project hosted on GitHub or GitLab, from the
+//! JSON their releases API returns, saved to a file:
+//!
+//! - GitHub: `GET /repos/{owner}/{repo}/releases`, each release with
+//!   `tag_name`, `body`, `published_at`, `draft` and `html_url`
+//! - GitLab: `GET /projects/{id}/releases`, each release with `tag_name`,
+//!   `description`, `released_at`, `upcoming_release` and `_links.self`
+//!
+//! The Markdown of each release is sorted into sections as `migrate` does:
+//! by its headings, like `### Bug fixes`, and by the prefix of its items,
+//! like `feat: ...`. Drafts and upcoming releases make the Unreleased one.
+
+use std::str::FromStr;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::changelog::{Changelog, HeadingStyle, Release};
+use crate::date::Date;
+use crate::error::ChangelogError;
+use crate::locale::Locale;
+use crate::migrate::Sorter;
+use crate::parse::{self, BlockKind};
+
+/// Service a dump of releases comes from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReleaseHost {
+    GitHub,
+    GitLab,
+}
+
+impl ReleaseHost {
+    pub const ALL: [ReleaseHost; 2] = [ReleaseHost::GitHub, ReleaseHost::GitLab];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ReleaseHost::GitHub => "github-releases",
+            ReleaseHost::GitLab => "gitlab-releases",
+        }
+    }
+}
+
+impl FromStr for ReleaseHost {
+    type Err = ChangelogError;
+
+    fn from_str(s: &str) -> Result<ReleaseHost, ChangelogError> {
+        ReleaseHost::ALL
+            .iter()
+            .copied()
+            .find(|host| host.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ChangelogError::Validation(format!("unknown release host '{}'", s)))
+    }
+}
+
+#[derive(Deserialize)]
+struct GitHubRelease {
+    tag_name: String,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    draft: bool,
+    #[serde(default)]
+    published_at: Option<String>,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default)]
+    html_url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitLabLinks {
+    #[serde(default, rename = "self")]
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct GitLabRelease {
+    tag_name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    upcoming_release: bool,
+    #[serde(default)]
+    released_at: Option<String>,
+    #[serde(default)]
+    created_at: Option<String>,
+    #[serde(default, rename = "_links")]
+    links: Option<GitLabLinks>,
+}
+
+/// A release as both services describe it.
+struct HostRelease {
+    tag: String,
+    markdown: String,
+    upcoming: bool,
+    timestamp: Option<String>,
+    url: Option<String>,
+}
+
+impl From<GitHubRelease> for HostRelease {
+    fn from(release: GitHubRelease) -> HostRelease {
+        HostRelease {
+            tag: release.tag_name,
+            markdown: release.body.unwrap_or_default(),
+            upcoming: release.draft,
+            timestamp: release.published_at.or(release.created_at),
+            url: release.html_url,
+        }
+    }
+}
+
+impl From<GitLabRelease> for HostRelease {
+    fn from(release: GitLabRelease) -> HostRelease {
+        HostRelease {
+            tag: release.tag_name,
+            markdown: release.description.unwrap_or_default(),
+            upcoming: release.upcoming_release,
+            timestamp: release.released_at.or(release.created_at),
+            url: release.links.and_then(|links| links.url),
+        }
+    }
+}
+
+/// Version of a tag, without the `v` of `v1.2.3`.
+fn tag_version(tag: &str) -> Result<String, ChangelogError> {
+    let tag = tag.trim();
+    let version = match tag.strip_prefix(&['v', 'V'][..]) {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest,
+        _ => tag,
+    };
+    if version.is_empty() || version.contains(&['[', ']', '\n'][..]) {
+        return Err(ChangelogError::Validation(format!(
+            "tag '{}' cannot be the version of a release",
+            tag
+        )));
+    }
+    Ok(version.to_string())
+}
+
+impl Changelog {
+    /// Reads the releases of `host` from the JSON of its API, with notes on
+    /// what could not be classified, each starting with its release.
+    ///
+    /// The link of each release compares it with the previous one when its
+    /// notes end with GitHub's `**Full Changelog**: URL`, and goes to its
+    /// page otherwise.
+    pub fn from_releases(
+        input: &str,
+        host: ReleaseHost,
+        locale: &'static Locale,
+    ) -> Result<(Changelog, Vec<String>), ChangelogError> {
+        lazy_static! {
+            static ref FULL_CHANGELOG: Regex =
+                Regex::new(r"^\*\*Full Changelog\*\*:?\s*<?(?P<url>https?://[^\s>]+)>?\s*$")
+                    .unwrap();
+        }
+
+        let error = |err: serde_json::Error| ChangelogError::Parse {
+            line: err.line(),
+            message: err.to_string(),
+        };
+        let host_releases: Vec<HostRelease> = match host {
+            ReleaseHost::GitHub => serde_json::from_str::<Vec<GitHubRelease>>(input)
+                .map_err(error)?
+                .into_iter()
+                .map(HostRelease::from)
+                .collect(),
+            ReleaseHost::GitLab => serde_json::from_str::<Vec<GitLabRelease>>(input)
+                .map_err(error)?
+                .into_iter()
+                .map(HostRelease::from)
+                .collect(),
+        };
+
+        let mut unreleased = Release::unreleased(locale);
+        let mut releases = vec![];
+        let mut links = vec![];
+        let mut notes = vec![];
+
+        for host_release in host_releases {
+            let mut release_notes = vec![];
+            let mut release = if host_release.upcoming {
+                Release::unreleased(locale)
+            } else {
+                let date = host_release
+                    .timestamp
+                    .as_deref()
+                    .and_then(|timestamp| timestamp.split('T').next())
+                    .and_then(|date| date.parse::<Date>().ok());
+                if date.is_none() {
+                    release_notes.push(String::from("no release date"));
+                }
+                Release {
+                    version: tag_version(&host_release.tag)?,
+                    date: date.map(|date| date.to_string()),
+                    unreleased: false,
+                    yanked: false,
+                    description: String::new(),
+                    sections: vec![],
+                    style: HeadingStyle::default(),
+                }
+            };
+
+            let markdown = host_release
+                .markdown
+                .replace("\r\n", "\n")
+                .replace('\r', "\n");
+            let mut url = host_release.url;
+            let mut sorter = Sorter::default();
+            for block in parse::blocks(&markdown) {
+                let raw = &markdown[block.range.clone()];
+                if block.kind == BlockKind::Other {
+                    if let Some(captures) = FULL_CHANGELOG.captures(raw.trim()) {
+                        url = Some(captures["url"].to_string());
+                        continue;
+                    }
+                }
+                sorter.push(&mut release, &markdown, &block, locale, &mut release_notes);
+            }
+
+            if release.unreleased {
+                // the notes of drafts all go to the one Unreleased release
+                for section in release.sections {
+                    match unreleased
+                        .sections
+                        .iter_mut()
+                        .find(|s| s.kind == section.kind)
+                    {
+                        Some(existing) => existing.entries.extend(section.entries),
+                        None => unreleased.sections.push(section),
+                    }
+                }
+                unreleased.sections.sort_by_key(|s| s.kind);
+                if !release.description.is_empty() {
+                    parse::append(
+                        &mut unreleased.description,
+                        &release.description,
+                        &(0..release.description.len()),
+                        None,
+                    );
+                }
+            } else {
+                if let Some(url) = url {
+                    links.push((release.version.clone(), url));
+                }
+                releases.push(release);
+            }
+            let tag = &host_release.tag;
+            notes.extend(
+                release_notes
+                    .into_iter()
+                    .map(|note| format!("release {}: {}", tag, note)),
+            );
+        }
+
+        // newest first, whatever the order of the dump
+        releases.sort_by(|a: &Release, b: &Release| b.date.cmp(&a.date));
+
+        let mut changelog = Changelog::new(locale);
+        changelog.releases = std::iter::once(unreleased).chain(releases).collect();
+        changelog.links = links;
+
+        let reparsed = Changelog::parse_with_locale(&changelog.to_string(), locale).ok();
+        if reparsed.as_ref() != Some(&changelog) {
+            return Err(ChangelogError::Validation(String::from(
+                "the release notes do not make a valid changelog, e.g. a code block is not closed",
+            )));
+        }
+        Ok((changelog, notes))
+    }
+}
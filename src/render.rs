@@ -0,0 +1,323 @@
+//! Rendering of a changelog into other markup languages.
+//!
+//! Each backend is a writer that takes an iterator of pulldown-cmark events
+//! as input, on top of an [`Output`](struct.Output.html) that lays out the
+//! blocks and indents the lines of nested ones.
+
+use std::io::{self, Write};
+use std::str::FromStr;
+
+use pulldown_cmark::{Event, HeadingLevel, Tag};
+
+use crate::changelog::{Changelog, Release};
+use crate::error::ChangelogError;
+use crate::text::{self, TextOptions};
+use crate::{asciidoc, man, rst};
+
+/// This wrapper exists because we can't have both a blanket implementation
+/// for all types implementing `Write` and types of the for `&mut W` where
+/// `W: StrWrite`. Since we need the latter a lot, we choose to wrap
+/// `Write` types.
+pub(crate) struct WriteWrapper<W>(pub(crate) W);
+
+/// Trait that allows writing string slices. This is basically an extension
+/// of `std::io::Write` in order to include `String`.
+pub(crate) trait StrWrite {
+    fn write_str(&mut self, s: &str) -> io::Result<()>;
+}
+
+impl<W> StrWrite for WriteWrapper<W>
+where
+    W: Write,
+{
+    #[inline]
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        self.0.write_all(s.as_bytes())
+    }
+}
+
+impl StrWrite for String {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl<W> StrWrite for &'_ mut W
+where
+    W: StrWrite,
+{
+    #[inline]
+    fn write_str(&mut self, s: &str) -> io::Result<()> {
+        (**self).write_str(s)
+    }
+}
+
+/// Options of `Changelog::render_with`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderOptions {
+    /// Name of a man page, e.g. `foo-changelog`, which defaults to its
+    /// title in lowercase, with hyphens between the words.
+    pub name: Option<String>,
+}
+
+/// Lines and blocks of a rendered document.
+pub(crate) struct Output<W> {
+    /// Writer to write to.
+    writer: W,
+
+    /// Whether or not the last write wrote a newline.
+    pub(crate) end_newline: bool,
+
+    /// Whether a block just ended, so the next one needs a blank line.
+    pub(crate) end_block: bool,
+
+    /// Whether a list marker was just written, so the first block of the
+    /// item goes on the same line.
+    pub(crate) after_marker: bool,
+
+    /// Last character written, if any.
+    pub(crate) last_char: Option<char>,
+
+    /// Prefix of every line: item indentation and quote markers.
+    indent: String,
+
+    /// Length of `indent` outside of each open container.
+    containers: Vec<usize>,
+}
+
+impl<W> Output<W>
+where
+    W: StrWrite,
+{
+    pub(crate) fn new(writer: W) -> Self {
+        Self {
+            writer,
+            end_newline: true,
+            end_block: false,
+            after_marker: false,
+            last_char: None,
+            indent: String::new(),
+            containers: vec![],
+        }
+    }
+
+    /// Writes a new line, prefixing blank ones with quote markers.
+    pub(crate) fn write_newline(&mut self) -> io::Result<()> {
+        if self.end_newline {
+            self.writer.write_str(self.indent.trim_end())?;
+        }
+        self.end_newline = true;
+        self.writer.write_str("\n")
+    }
+
+    /// Writes a buffer, indenting every line, and tracks whether or not a
+    /// newline was written.
+    pub(crate) fn write(&mut self, s: &str) -> io::Result<()> {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.write_newline()?;
+            }
+            if !line.is_empty() {
+                if self.end_newline {
+                    self.writer.write_str(&self.indent)?;
+                }
+                self.writer.write_str(line)?;
+                self.last_char = line.chars().last();
+                self.end_newline = false;
+                self.after_marker = false;
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves to a new line, leaving a blank one after a previous block.
+    pub(crate) fn start_block(&mut self) -> io::Result<()> {
+        if self.after_marker {
+            self.after_marker = false;
+            return Ok(());
+        }
+        self.end_line()?;
+        if self.end_block {
+            self.write_newline()?;
+        }
+        self.end_block = false;
+        Ok(())
+    }
+
+    pub(crate) fn end_block(&mut self) -> io::Result<()> {
+        self.end_line()?;
+        self.end_block = true;
+        Ok(())
+    }
+
+    /// Moves to a new line unless at the start of one.
+    pub(crate) fn end_line(&mut self) -> io::Result<()> {
+        if !self.end_newline {
+            self.write_newline()?;
+        }
+        Ok(())
+    }
+
+    /// Width of the prefix of the lines.
+    pub(crate) fn indent_width(&self) -> usize {
+        self.indent.chars().count()
+    }
+
+    pub(crate) fn push_container(&mut self, prefix: &str) {
+        self.containers.push(self.indent.len());
+        self.indent.push_str(prefix);
+    }
+
+    pub(crate) fn pop_container(&mut self) {
+        let len = self.containers.pop().unwrap_or(0);
+        self.indent.truncate(len);
+    }
+}
+
+/// Markup language a changelog can be rendered into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderFormat {
+    Markdown,
+    Text,
+    Html,
+    Asciidoc,
+    Rst,
+    Man,
+}
+
+impl RenderFormat {
+    pub const ALL: [RenderFormat; 6] = [
+        RenderFormat::Markdown,
+        RenderFormat::Text,
+        RenderFormat::Html,
+        RenderFormat::Asciidoc,
+        RenderFormat::Rst,
+        RenderFormat::Man,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderFormat::Markdown => "markdown",
+            RenderFormat::Text => "text",
+            RenderFormat::Html => "html",
+            RenderFormat::Asciidoc => "asciidoc",
+            RenderFormat::Rst => "rst",
+            RenderFormat::Man => "man",
+        }
+    }
+}
+
+impl FromStr for RenderFormat {
+    type Err = ChangelogError;
+
+    fn from_str(s: &str) -> Result<RenderFormat, ChangelogError> {
+        RenderFormat::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ChangelogError::Validation(format!("unknown render format '{}'", s)))
+    }
+}
+
+impl Changelog {
+    /// Events of the preamble, if asked for, and of `releases`, with the
+    /// headings of the releases as Markdown and anchored as on GitHub.
+    ///
+    /// `headings` has the heading and the anchor of each release.
+    fn rendered_events<'a>(
+        &'a self,
+        preamble: bool,
+        releases: &[&'a Release],
+        headings: &'a [(String, String)],
+    ) -> Vec<Event<'a>> {
+        let mut events = if preamble {
+            self.markdown_events(&self.preamble, false)
+        } else {
+            vec![]
+        };
+        for (release, (heading, anchor)) in releases.iter().zip(headings) {
+            let tag = || Tag::Heading(HeadingLevel::H2, Some(anchor.as_str()), vec![]);
+            events.push(Event::Start(tag()));
+            // the links of headings are resolved like those of the rest
+            events.extend(self.markdown_events(heading, true));
+            events.push(Event::End(tag()));
+            events.extend(self.release_events(release));
+        }
+        events
+    }
+
+    /// Writes the changelog in another markup language, with the headings
+    /// of the releases anchored as on GitHub.
+    ///
+    /// Markdown is written as by `write`, plain text as by `render_text`
+    /// with the default options, and a man page is in section 7, titled
+    /// after the first heading of the preamble.
+    pub fn render<W: Write>(&self, format: RenderFormat, writer: W) -> Result<(), ChangelogError> {
+        self.render_with(format, &RenderOptions::default(), writer)
+    }
+
+    /// Writes the changelog as `render` does, with `options`.
+    pub fn render_with<W: Write>(
+        &self,
+        format: RenderFormat,
+        options: &RenderOptions,
+        mut writer: W,
+    ) -> Result<(), ChangelogError> {
+        let releases: Vec<&Release> = self.releases.iter().collect();
+        let headings = headings(&releases);
+        let events = self.rendered_events(true, &releases, &headings).into_iter();
+
+        match format {
+            RenderFormat::Markdown => return self.write(writer),
+            RenderFormat::Text => {
+                text::write(&mut writer, events, TextOptions::default(), self.locale())?
+            }
+            RenderFormat::Html => pulldown_cmark::html::write_html(&mut writer, events)?,
+            RenderFormat::Asciidoc => asciidoc::write(&mut writer, events)?,
+            RenderFormat::Rst => rst::write(&mut writer, events)?,
+            RenderFormat::Man => {
+                // the page is as recent as the latest release
+                let title =
+                    crate::feed::title(&self.preamble).unwrap_or_else(|| String::from("Changelog"));
+                let date = self.latest().and_then(|r| r.date.as_deref()).unwrap_or("");
+                let name = match &options.name {
+                    Some(name) => name.clone(),
+                    None => title
+                        .split_whitespace()
+                        .collect::<Vec<_>>()
+                        .join("-")
+                        .to_lowercase(),
+                };
+                man::write(&mut writer, events, &name, &title, date)?
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the changelog as plain text, or only `release` if given.
+    pub fn render_text<W: Write>(
+        &self,
+        release: Option<&Release>,
+        options: TextOptions,
+        mut writer: W,
+    ) -> Result<(), ChangelogError> {
+        let releases: Vec<&Release> = match release {
+            Some(release) => vec![release],
+            None => self.releases.iter().collect(),
+        };
+        let headings = headings(&releases);
+        let events = self.rendered_events(release.is_none(), &releases, &headings);
+        text::write(&mut writer, events.into_iter(), options, self.locale())?;
+        Ok(())
+    }
+}
+
+/// Heading and anchor of each release.
+fn headings(releases: &[&Release]) -> Vec<(String, String)> {
+    releases
+        .iter()
+        .map(|release| (release.heading(), release.anchor()))
+        .collect()
+}
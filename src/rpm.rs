@@ -0,0 +1,124 @@
+//! The `%changelog` section of an RPM spec file.
+//!
+//! Each release is an entry, newest first:
+//!
+//! ```text
+//! * Tue Jun 20 2017 Jane Doe <jane@example.com> - 1.0.0-1
+//! - New visual identity by @tylerfortune8.
+//! - Version navigation.
+//! ```
+//!
+//! Entries are written as plain text and wrapped at 80 columns, optionally
+//! prefixed with the title of their section, e.g. `- [Fixed] ...`.
+
+use regex::Regex;
+
+use crate::changelog::Changelog;
+use crate::date::Date;
+use crate::debian::push_change;
+use crate::error::ChangelogError;
+use crate::schema::plain_text;
+
+/// What an RPM changelog says that a Keep a Changelog file does not.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RpmOptions {
+    /// Packager, as `Full Name <email@example.com>`.
+    pub packager: String,
+
+    /// Release of the packages, appended to the versions, e.g. `1`.
+    pub release: String,
+
+    /// Whether entries start with the title of their section.
+    pub kinds: bool,
+}
+
+impl RpmOptions {
+    fn validate(&self) -> Result<(), ChangelogError> {
+        lazy_static! {
+            static ref RELEASE: Regex = Regex::new(r"^[A-Za-z0-9._+~^%{}?!]+$").unwrap();
+            static ref PACKAGER: Regex = Regex::new(r"^[^<>]*\S <[^<>\s]+@[^<>\s]+>$").unwrap();
+        }
+
+        if !RELEASE.is_match(&self.release) {
+            return Err(ChangelogError::Validation(format!(
+                "'{}' is not an RPM release",
+                self.release
+            )));
+        }
+        if !PACKAGER.is_match(&self.packager) {
+            return Err(ChangelogError::Validation(format!(
+                "packager '{}' is not 'Full Name <email>'",
+                self.packager
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// RPM version of a release: a tag-like `v` is dropped, and a `-` starting
+/// a prerelease becomes `~` so that it sorts before the release.
+fn rpm_version(version: &str) -> Result<String, ChangelogError> {
+    lazy_static! {
+        static ref VERSION: Regex =
+            Regex::new(r"^v?(?P<version>[0-9][A-Za-z0-9._+]*)(?:-(?P<pre>[A-Za-z0-9._]+))?$")
+                .unwrap();
+    }
+
+    let captures = VERSION.captures(version).ok_or_else(|| {
+        ChangelogError::Validation(format!("'{}' is not an RPM version", version))
+    })?;
+    Ok(match captures.name("pre") {
+        Some(pre) => format!("{}~{}", &captures["version"], pre.as_str()),
+        None => captures["version"].to_string(),
+    })
+}
+
+impl Changelog {
+    /// The releases as the `%changelog` of an RPM spec file; the Unreleased
+    /// changes are left out, and every release needs a date.
+    pub fn to_rpm(&self, options: &RpmOptions) -> Result<String, ChangelogError> {
+        options.validate()?;
+
+        let mut out = String::new();
+        for release in self.releases.iter().filter(|r| !r.unreleased) {
+            let date: Date = release
+                .date
+                .as_deref()
+                .ok_or_else(|| {
+                    ChangelogError::Validation(format!("release {} has no date", release.version))
+                })?
+                .parse()?;
+
+            if !out.is_empty() {
+                out.push('\n');
+            }
+            out.push_str(&format!(
+                "* {} {} {:02} {:04} {} - {}-{}\n",
+                date.weekday_name(),
+                date.month_name(),
+                date.day,
+                date.year,
+                options.packager,
+                rpm_version(&release.version)?,
+                options.release
+            ));
+
+            let mut empty = true;
+            for section in &release.sections {
+                for entry in &section.entries {
+                    let mut text = plain_text(&entry.text);
+                    if options.kinds {
+                        text = format!("[{}] {}", section.title, text);
+                    }
+                    // macros would be expanded by rpmbuild
+                    push_change(&mut out, &text.replace('%', "%%"), "-", " ");
+                    empty = false;
+                }
+            }
+            if empty {
+                out.push_str("- New upstream release.\n");
+            }
+        }
+        Ok(out)
+    }
+}
@@ -0,0 +1,408 @@
+//! reStructuredText renderer that takes an iterator of events as input.
+//!
+//! reStructuredText has no nested inline markup, so markup inside another
+//! is written as plain text, and the underlines of headings are as long as
+//! their text, which is buffered until the end of the heading.
+
+use std::io::{self, Write};
+
+use pulldown_cmark::Event::*;
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, LinkType, Tag};
+
+use crate::render::{Output, StrWrite, WriteWrapper};
+
+/// Characters underlining headings, by level; titles are also overlined.
+const UNDERLINES: [char; 6] = ['=', '-', '~', '^', '"', '\''];
+
+struct RstWriter<I, W> {
+    /// Iterator supplying events.
+    iter: I,
+
+    out: Output<W>,
+
+    /// Text of the heading being written, if any.
+    heading: Option<String>,
+
+    /// Whether the last event was a block of raw HTML.
+    html_block: bool,
+
+    /// Whether the events are in a code block, written verbatim.
+    in_code: bool,
+
+    /// Whether the events are in a table cell, quoted as in CSV.
+    in_cell: bool,
+
+    /// Whether the next cell is the first of its row.
+    first_cell: bool,
+
+    /// Whether the last write ended inline markup, which text must not
+    /// follow right away.
+    end_markup: bool,
+
+    /// Whether markup was written for each open inline element.
+    inline: Vec<bool>,
+
+    /// Next number of each open list, `None` for bullet lists.
+    lists: Vec<Option<u64>>,
+}
+
+/// Escapes the characters of text that would start inline markup.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if let '\\' | '*' | '`' | '_' | '|' = c {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl<'a, I, W> RstWriter<I, W>
+where
+    I: Iterator<Item = Event<'a>>,
+    W: StrWrite,
+{
+    fn new(iter: I, writer: W) -> Self {
+        Self {
+            iter,
+            out: Output::new(writer),
+            heading: None,
+            html_block: false,
+            in_code: false,
+            in_cell: false,
+            first_cell: false,
+            end_markup: false,
+            inline: vec![],
+            lists: vec![],
+        }
+    }
+
+    /// Writes inline content, into the heading if one is open.
+    fn write(&mut self, s: &str) -> io::Result<()> {
+        let s = if self.in_cell {
+            s.replace('"', "\"\"")
+        } else {
+            s.to_string()
+        };
+        // text right after inline markup must be set apart from it
+        let start = match &self.heading {
+            Some(heading) => heading.is_empty(),
+            None => self.out.end_newline,
+        };
+        let s = if self.end_markup && !start && s.starts_with(|c: char| c.is_alphanumeric()) {
+            format!("\\ {}", s)
+        } else {
+            s
+        };
+        self.end_markup = false;
+
+        match &mut self.heading {
+            Some(heading) => {
+                heading.push_str(&s);
+                Ok(())
+            }
+            None => self.out.write(&s),
+        }
+    }
+
+    /// Writes the start of inline markup, unless in another.
+    fn start_markup(&mut self, markup: &str) -> io::Result<()> {
+        let written = self.inline.is_empty();
+        self.inline.push(written);
+        if written {
+            // as must markup right after a word
+            let glued = match &self.heading {
+                Some(heading) => heading.ends_with(|c: char| c.is_alphanumeric()),
+                None => {
+                    !self.out.end_newline && self.out.last_char.is_some_and(char::is_alphanumeric)
+                }
+            };
+            if glued {
+                self.write("\\ ")?;
+            }
+            self.write(markup)?;
+        }
+        Ok(())
+    }
+
+    fn end_markup(&mut self, markup: &str) -> io::Result<()> {
+        if self.inline.pop().unwrap_or(false) {
+            self.write(markup)?;
+            self.end_markup = true;
+        }
+        Ok(())
+    }
+
+    pub fn run(mut self) -> io::Result<()> {
+        while let Some(event) = self.iter.next() {
+            if self.html_block && !matches!(event, Html(_)) {
+                self.html_block = false;
+                self.out.pop_container();
+                self.out.end_block()?;
+            }
+            match event {
+                Start(tag) => {
+                    self.start_tag(tag)?;
+                }
+                End(tag) => {
+                    self.end_tag(tag)?;
+                }
+                Text(text) => {
+                    if self.in_code {
+                        self.write(&text)?;
+                    } else {
+                        self.write(&escape(&text))?;
+                    }
+                }
+                Code(text) => {
+                    if self.inline.is_empty() && !text.contains("``") {
+                        self.start_markup("``")?;
+                        self.write(&text)?;
+                        self.end_markup("``")?;
+                    } else {
+                        self.write(&escape(&text))?;
+                    }
+                }
+                Html(html) => {
+                    if html.ends_with('\n') && !self.html_block {
+                        self.out.start_block()?;
+                        self.out.write(".. raw:: html\n\n")?;
+                        self.out.push_container("   ");
+                        self.html_block = true;
+                    }
+                    self.write(&html)?;
+                }
+                SoftBreak | HardBreak => {
+                    if self.heading.is_some() {
+                        self.write(" ")?;
+                    } else {
+                        self.out.write_newline()?;
+                    }
+                }
+                Rule => {
+                    self.out.start_block()?;
+                    self.out.write("----")?;
+                    self.out.end_block()?;
+                }
+                FootnoteReference(name) => {
+                    self.write(&format!(" [#{}]_", name))?;
+                }
+                TaskListMarker(true) => {
+                    self.write("[x] ")?;
+                }
+                TaskListMarker(false) => {
+                    self.write("[ ] ")?;
+                }
+            }
+        }
+        if self.html_block {
+            self.out.pop_container();
+            self.out.end_block()?;
+        }
+        Ok(())
+    }
+
+    /// Writes the start of a reStructuredText element.
+    fn start_tag(&mut self, tag: Tag<'a>) -> io::Result<()> {
+        // inline markup ends with its block
+        if let Tag::Item | Tag::Paragraph | Tag::Heading(..) = tag {
+            self.end_markup = false;
+        }
+        match tag {
+            Tag::Paragraph => self.out.start_block(),
+            Tag::Heading(_, id, _) => {
+                self.out.start_block()?;
+                if let Some(id) = id {
+                    self.out.write(&format!(".. _`{}`:\n\n", id))?;
+                }
+                self.heading = Some(String::new());
+                Ok(())
+            }
+            Tag::Table(_) => {
+                self.out.start_block()?;
+                self.out.write(".. csv-table::\n   :header-rows: 1\n\n")?;
+                self.out.push_container("   ");
+                Ok(())
+            }
+            Tag::TableHead | Tag::TableRow => {
+                self.first_cell = true;
+                Ok(())
+            }
+            Tag::TableCell => {
+                if !self.first_cell {
+                    self.out.write(", ")?;
+                }
+                self.first_cell = false;
+                self.out.write("\"")?;
+                self.in_cell = true;
+                Ok(())
+            }
+            Tag::BlockQuote => {
+                self.out.start_block()?;
+                self.out.push_container("    ");
+                Ok(())
+            }
+            Tag::CodeBlock(info) => {
+                self.out.start_block()?;
+                match info {
+                    CodeBlockKind::Fenced(info) if !info.trim().is_empty() => {
+                        let language = info.split_whitespace().next().unwrap_or("");
+                        self.out
+                            .write(&format!(".. code-block:: {}\n\n", language))?;
+                    }
+                    _ => self.out.write("::\n\n")?,
+                }
+                self.out.push_container("   ");
+                self.in_code = true;
+                Ok(())
+            }
+            Tag::List(start) => {
+                // nested lists are set apart from the text of their item
+                if !self.lists.is_empty() {
+                    self.out.end_block = true;
+                }
+                self.out.start_block()?;
+                self.lists.push(start);
+                Ok(())
+            }
+            Tag::Item => {
+                self.out.end_line()?;
+                if self.out.end_block {
+                    self.out.write_newline()?;
+                    self.out.end_block = false;
+                }
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => String::from("- "),
+                };
+                self.out.write(&marker)?;
+                self.out.push_container(&" ".repeat(marker.len()));
+                self.out.after_marker = true;
+                Ok(())
+            }
+            Tag::Emphasis => self.start_markup("*"),
+            Tag::Strong => self.start_markup("**"),
+            Tag::Strikethrough => {
+                self.inline.push(false);
+                Ok(())
+            }
+            Tag::Link(LinkType::Autolink, _, _) | Tag::Link(LinkType::Email, _, _) => {
+                self.inline.push(false);
+                Ok(())
+            }
+            Tag::Link(..) | Tag::Image(..) => self.start_markup("`"),
+            Tag::FootnoteDefinition(name) => {
+                self.out.start_block()?;
+                self.out.write(&format!(".. [#{}] ", name))?;
+                self.out.push_container("   ");
+                self.out.after_marker = true;
+                Ok(())
+            }
+        }
+    }
+
+    fn end_tag(&mut self, tag: Tag) -> io::Result<()> {
+        match tag {
+            Tag::Heading(level, _, _) => {
+                let text = self.heading.take().unwrap_or_default();
+                let line: String = UNDERLINES[level as usize - 1]
+                    .to_string()
+                    .repeat(text.chars().count().max(1));
+                if level == HeadingLevel::H1 {
+                    self.out.write(&format!("{}\n", line))?;
+                }
+                self.out.write(&format!("{}\n{}", text, line))?;
+                self.out.end_block()?;
+            }
+            Tag::Paragraph => {
+                self.out.end_block()?;
+            }
+            Tag::Table(_) => {
+                self.out.pop_container();
+                self.out.end_block()?;
+            }
+            Tag::TableHead | Tag::TableRow => {
+                self.out.write_newline()?;
+            }
+            Tag::TableCell => {
+                self.in_cell = false;
+                self.out.write("\"")?;
+            }
+            Tag::BlockQuote | Tag::FootnoteDefinition(_) => {
+                self.out.pop_container();
+                self.out.end_block()?;
+            }
+            Tag::CodeBlock(_) => {
+                self.in_code = false;
+                self.out.pop_container();
+                self.out.end_block()?;
+            }
+            Tag::List(_) => {
+                self.lists.pop();
+                self.out.end_block()?;
+            }
+            Tag::Item => {
+                self.out.pop_container();
+                self.out.after_marker = false;
+                self.out.end_line()?;
+            }
+            Tag::Emphasis => {
+                self.end_markup("*")?;
+            }
+            Tag::Strong => {
+                self.end_markup("**")?;
+            }
+            Tag::Strikethrough
+            | Tag::Link(LinkType::Autolink, _, _)
+            | Tag::Link(LinkType::Email, _, _) => {
+                self.inline.pop();
+            }
+            Tag::Link(_, dest, _) | Tag::Image(_, dest, _) => {
+                if self.inline.last() == Some(&true) {
+                    self.write(&format!(" <{}>", dest))?;
+                }
+                self.end_markup("`__")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Iterate over an `Iterator` of `Event`s, generate reStructuredText for
+/// each `Event`, and write it out to a writable stream.
+pub(crate) fn write<'a, I, W>(writer: W, iter: I) -> io::Result<()>
+where
+    I: Iterator<Item = Event<'a>>,
+    W: Write,
+{
+    RstWriter::new(iter, WriteWrapper(writer)).run()
+}
+
+#[cfg(test)]
+mod tests {
+    use pulldown_cmark::Parser;
+
+    fn rst(markdown: &str) -> String {
+        let mut out = vec![];
+        super::write(&mut out, Parser::new(markdown)).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn markup_ends_with_its_item() {
+        assert_eq!(rst("- Fix `a`\n- Code b\n"), "- Fix ``a``\n- Code b\n");
+        assert_eq!(rst("- *a*\n- b\n"), "- *a*\n- b\n");
+        assert_eq!(rst("Some *a*\n\nCode\n"), "Some *a*\n\nCode\n");
+        assert_eq!(rst("Some *a*\n# Code\n"), "Some *a*\n\n====\nCode\n====\n");
+    }
+
+    #[test]
+    fn text_after_markup_is_set_apart() {
+        assert_eq!(rst("*a*b `c`d\n"), "*a*\\ b ``c``\\ d\n");
+        assert_eq!(rst("- *a*b\n"), "- *a*\\ b\n");
+    }
+}
@@ -0,0 +1,428 @@
+//! Structured form of a changelog, to export it as data and import it back.
+//!
+//! The schema is versioned by `schema_version`, which changes whenever a
+//! field is renamed or removed; new fields can be added within a version.
+//! Version 1 looks like this in JSON, and has the same structure in YAML
+//! and TOML:
+//!
+//! ```json
+//! {
+//!   "schema_version": 1,
+//!   "preamble": "# Changelog\n\nAll notable changes...",
+//!   "releases": [
+//!     {
+//!       "version": "1.0.0",
+//!       "date": "2017-06-20",
+//!       "unreleased": false,
+//!       "yanked": false,
+//!       "compare_url": "https://github.com/owner/repo/compare/v0.9.0...v1.0.0",
+//!       "version_style": "linked",
+//!       "date_style": "en-dash",
+//!       "description": "",
+//!       "sections": [
+//!         {
+//!           "title": "Added",
+//!           "kind": "added",
+//!           "description": "",
+//!           "entries": [
+//!             {
+//!               "markdown": "New visual identity by [@tylerfortune8](https://github.com/tylerfortune8).",
+//!               "text": "New visual identity by @tylerfortune8."
+//!             }
+//!           ]
+//!         }
+//!       ]
+//!     }
+//!   ],
+//!   "links": [
+//!     {
+//!       "label": "1.0.0",
+//!       "url": "https://github.com/owner/repo/compare/v0.9.0...v1.0.0"
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! - `preamble` and every `description` are Markdown, empty when missing.
+//! - `date` and `compare_url` are left out when missing.
+//! - `version_style` is how the heading writes the version: `bracketed`,
+//!   as `[1.0.0]`, `linked`, as `[1.0.0](compare_url)`, or `plain`.
+//!   `date_style` is what sets the date apart: `hyphen`, `en-dash`,
+//!   `em-dash` or `parenthesized`. Both are left out for the standard
+//!   `[1.0.0] - 2017-06-20`.
+//! - `kind` is the lowercase name of a standard section, left out for other
+//!   sections.
+//! - An entry's `markdown` is the bullet without its list marker, `text` the
+//!   same without formatting.
+//! - `links` are all the link reference definitions, compare URLs included.
+//!
+//! On import, only `schema_version`, `version` and `title` are required,
+//! `text` is ignored, and a missing `kind` is found from the title. A
+//! `compare_url` that is not in `links` is added to them, unless the
+//! heading is `linked` to it.
+
+use std::str::FromStr;
+
+use pulldown_cmark::{Event, Parser, Tag};
+use serde::{Deserialize, Serialize};
+
+use crate::changelog::{self, Changelog, DateStyle, HeadingStyle, Kind, VersionStyle};
+use crate::error::ChangelogError;
+use crate::locale::Locale;
+use crate::parse;
+
+/// Version of the schema written by `Changelog::export`.
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Document {
+    pub schema_version: u32,
+
+    #[serde(default)]
+    pub preamble: String,
+
+    #[serde(default)]
+    pub releases: Vec<Release>,
+
+    #[serde(default)]
+    pub links: Vec<Link>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+
+    #[serde(default)]
+    pub unreleased: bool,
+
+    #[serde(default)]
+    pub yanked: bool,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compare_url: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_style: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_style: Option<String>,
+
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default)]
+    pub sections: Vec<Section>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Section {
+    pub title: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default)]
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub markdown: String,
+
+    #[serde(default)]
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Link {
+    pub label: String,
+    pub url: String,
+}
+
+/// Data format of an export or import.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Json, Format::Yaml, Format::Toml];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Json => "json",
+            Format::Yaml => "yaml",
+            Format::Toml => "toml",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = ChangelogError;
+
+    fn from_str(s: &str) -> Result<Format, ChangelogError> {
+        Format::ALL
+            .iter()
+            .copied()
+            .find(|format| format.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ChangelogError::Validation(format!("unknown format '{}'", s)))
+    }
+}
+
+/// Text of some Markdown, without its formatting.
+pub(crate) fn plain_text(markdown: &str) -> String {
+    let mut text = String::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(s) | Event::Code(s) => text.push_str(&s),
+            Event::SoftBreak => text.push(' '),
+            Event::HardBreak => text.push('\n'),
+            Event::End(Tag::Paragraph)
+            | Event::End(Tag::Item)
+            | Event::End(Tag::Heading(..))
+            | Event::End(Tag::CodeBlock(_))
+                if !text.ends_with('\n') =>
+            {
+                text.push('\n')
+            }
+            _ => (),
+        }
+    }
+    text.trim_end().to_string()
+}
+
+impl From<&changelog::Entry> for Entry {
+    fn from(entry: &changelog::Entry) -> Entry {
+        Entry {
+            markdown: entry.text.clone(),
+            text: plain_text(&entry.text),
+        }
+    }
+}
+
+impl From<&changelog::Section> for Section {
+    fn from(section: &changelog::Section) -> Section {
+        Section {
+            title: section.title.clone(),
+            kind: section.kind.map(|kind| kind.name().to_string()),
+            description: section.description.clone(),
+            entries: section.entries.iter().map(Entry::from).collect(),
+        }
+    }
+}
+
+impl From<&Changelog> for Document {
+    fn from(changelog: &Changelog) -> Document {
+        Document {
+            schema_version: SCHEMA_VERSION,
+            preamble: changelog.preamble.clone(),
+            releases: changelog
+                .releases
+                .iter()
+                .map(|release| {
+                    let (version_style, compare_url) = match &release.style.version {
+                        VersionStyle::Bracketed => {
+                            (None, changelog.link(&release.version).map(String::from))
+                        }
+                        VersionStyle::Linked(url) => (Some("linked"), Some(url.clone())),
+                        VersionStyle::Plain => (
+                            Some("plain"),
+                            changelog.link(&release.version).map(String::from),
+                        ),
+                    };
+                    Release {
+                        version: release.version.clone(),
+                        date: release.date.clone(),
+                        unreleased: release.unreleased,
+                        yanked: release.yanked,
+                        compare_url,
+                        version_style: version_style.map(String::from),
+                        date_style: match release.style.date {
+                            DateStyle::Hyphen => None,
+                            style => Some(style.name().to_string()),
+                        },
+                        description: release.description.clone(),
+                        sections: release.sections.iter().map(Section::from).collect(),
+                    }
+                })
+                .collect(),
+            links: changelog
+                .links
+                .iter()
+                .map(|(label, url)| Link {
+                    label: label.clone(),
+                    url: url.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Document {
+    /// Reads a document in `format`.
+    pub fn parse(input: &str, format: Format) -> Result<Document, ChangelogError> {
+        let error = |line: Option<usize>, message: String| ChangelogError::Parse {
+            line: line.unwrap_or(1),
+            message,
+        };
+        match format {
+            Format::Json => {
+                serde_json::from_str(input).map_err(|err| error(Some(err.line()), err.to_string()))
+            }
+            Format::Yaml => serde_yaml::from_str(input)
+                .map_err(|err| error(err.location().map(|l| l.line()), err.to_string())),
+            Format::Toml => toml::from_str(input)
+                .map_err(|err| error(err.line_col().map(|(l, _)| l + 1), err.to_string())),
+        }
+    }
+
+    /// The changelog this document describes, with headings in `locale`
+    /// where the document does not give them.
+    pub fn into_changelog(self, locale: &'static Locale) -> Result<Changelog, ChangelogError> {
+        if self.schema_version == 0 || self.schema_version > SCHEMA_VERSION {
+            return Err(ChangelogError::Validation(format!(
+                "schema version {} is not supported, the latest is {}",
+                self.schema_version, SCHEMA_VERSION
+            )));
+        }
+
+        let mut links: Vec<(String, String)> = self
+            .links
+            .into_iter()
+            .map(|link| (link.label, link.url))
+            .collect();
+        let mut releases = vec![];
+
+        for release in self.releases {
+            let version_style = match (release.version_style.as_deref(), &release.compare_url) {
+                (None, _) => VersionStyle::Bracketed,
+                (Some(style), _) if style.eq_ignore_ascii_case("bracketed") => {
+                    VersionStyle::Bracketed
+                }
+                (Some(style), _) if style.eq_ignore_ascii_case("plain") => VersionStyle::Plain,
+                (Some(style), Some(url)) if style.eq_ignore_ascii_case("linked") => {
+                    VersionStyle::Linked(url.clone())
+                }
+                (Some(style), None) if style.eq_ignore_ascii_case("linked") => {
+                    return Err(ChangelogError::Validation(format!(
+                        "release {} is linked but has no compare_url",
+                        release.version
+                    )))
+                }
+                (Some(style), _) => {
+                    return Err(ChangelogError::Validation(format!(
+                        "unknown version style '{}'",
+                        style
+                    )))
+                }
+            };
+            let date_style = match &release.date_style {
+                Some(style) => style.parse::<DateStyle>()?,
+                None => DateStyle::Hyphen,
+            };
+
+            if let (Some(url), VersionStyle::Bracketed | VersionStyle::Plain) =
+                (&release.compare_url, &version_style)
+            {
+                if !links
+                    .iter()
+                    .any(|(l, _)| l.eq_ignore_ascii_case(&release.version))
+                {
+                    links.push((release.version.clone(), url.clone()));
+                }
+            }
+
+            let mut sections = vec![];
+            for section in release.sections {
+                let kind = match &section.kind {
+                    Some(kind) => Some(kind.parse::<Kind>()?),
+                    None => locale.kind(&section.title),
+                };
+                let mut entries = vec![];
+                for entry in section.entries {
+                    let entry = changelog::Entry::new(&entry.markdown);
+                    if !parse::is_item(&entry.text) {
+                        return Err(ChangelogError::Validation(format!(
+                            "entry '{}' of release {} does not fit in a single list item",
+                            entry.text, release.version
+                        )));
+                    }
+                    entries.push(entry);
+                }
+                sections.push(changelog::Section {
+                    title: section.title,
+                    kind,
+                    description: section.description,
+                    entries,
+                });
+            }
+
+            let unreleased = release.unreleased || locale.is_unreleased(&release.version);
+            releases.push(changelog::Release {
+                version: if unreleased && !locale.is_unreleased(&release.version) {
+                    locale.unreleased.to_string()
+                } else {
+                    release.version
+                },
+                unreleased,
+                date: release.date,
+                yanked: release.yanked,
+                description: release.description,
+                sections,
+                style: HeadingStyle {
+                    version: version_style,
+                    date: date_style,
+                },
+            });
+        }
+
+        let changelog = Changelog {
+            preamble: self.preamble,
+            releases,
+            links,
+            locale,
+        };
+
+        // what the Markdown of descriptions does is up to the document, as
+        // long as the file written from it reads back the same
+        let reparsed = Changelog::parse_with_locale(&changelog.to_string(), locale).ok();
+        if reparsed.as_ref() != Some(&changelog) {
+            return Err(ChangelogError::Validation(String::from(
+                "the document does not make a valid changelog, e.g. a description contains headings",
+            )));
+        }
+        Ok(changelog)
+    }
+}
+
+impl Changelog {
+    /// The changelog as data in `format`, following the schema above.
+    pub fn export(&self, format: Format) -> Result<String, ChangelogError> {
+        let document = Document::from(self);
+        match format {
+            Format::Json => serde_json::to_string_pretty(&document)
+                .map_err(|err| ChangelogError::Validation(err.to_string())),
+            Format::Yaml => serde_yaml::to_string(&document)
+                .map_err(|err| ChangelogError::Validation(err.to_string())),
+            Format::Toml => toml::to_string_pretty(&document)
+                .map_err(|err| ChangelogError::Validation(err.to_string())),
+        }
+    }
+
+    /// Reads a changelog from data in `format`, following the schema above.
+    pub fn import(
+        input: &str,
+        format: Format,
+        locale: &'static Locale,
+    ) -> Result<Changelog, ChangelogError> {
+        Document::parse(input, format)?.into_changelog(locale)
+    }
+}
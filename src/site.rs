@@ -0,0 +1,226 @@
+//! Static site of a changelog: an index of the releases and a page for each.
+//!
+//! The pages are rendered with [TinyTemplate](https://docs.rs/tinytemplate)
+//! from `index.html` and `release.html`, and `style.css` is copied along.
+//! A template directory can replace any of these files; the defaults are in
+//! `templates/site`. The index is given:
+//!
+//! - `title`: text of the preamble's first heading
+//! - `preamble`: HTML of the preamble
+//! - `releases`: the releases, newest first, each with
+//!   - `version`, `date` (if any), `unreleased`, `yanked`
+//!   - `anchor`: id of the release's heading, as on GitHub
+//!   - `permalink`: path of the release's page, e.g. `releases/1.0.0.html`
+//!   - `summary`: number of entries per section, e.g. `2 added, 1 fixed`
+//!   - `description`: HTML of the description
+//!   - `sections`: each with `title`, `kind` (if standard), `anchor`,
+//!     `description` as HTML and `entries`, each with `id`, `html` and `text`
+//!
+//! A release page is given `title` and the page's `release`.
+//!
+//! `search.json` lists every entry, with its `version`, `section`, `text` and
+//! `url`.
+
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use tinytemplate::TinyTemplate;
+
+use crate::changelog::{Changelog, Release};
+use crate::error::ChangelogError;
+
+const INDEX: &str = include_str!("../templates/site/index.html");
+const RELEASE: &str = include_str!("../templates/site/release.html");
+const STYLE: &str = include_str!("../templates/site/style.css");
+
+#[derive(Serialize)]
+struct EntryPage {
+    id: String,
+    html: String,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct SectionPage {
+    title: String,
+    kind: Option<&'static str>,
+    anchor: String,
+    description: String,
+    entries: Vec<EntryPage>,
+}
+
+#[derive(Serialize)]
+struct ReleasePage {
+    version: String,
+    date: Option<String>,
+    unreleased: bool,
+    yanked: bool,
+    anchor: String,
+    permalink: String,
+    summary: String,
+    description: String,
+    sections: Vec<SectionPage>,
+}
+
+#[derive(Serialize)]
+struct Index<'a> {
+    title: &'a str,
+    preamble: String,
+    releases: &'a [ReleasePage],
+}
+
+#[derive(Serialize)]
+struct Page<'a> {
+    title: &'a str,
+    release: &'a ReleasePage,
+}
+
+#[derive(Serialize)]
+struct SearchEntry<'a> {
+    version: &'a str,
+    section: &'a str,
+    text: &'a str,
+    url: String,
+}
+
+/// Name of a file or anchor made of `text`: lowercase letters, digits, `.`,
+/// `_` and `-`.
+fn slug(text: &str) -> String {
+    let slug: String = text
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            c if c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-' => c,
+            _ => '-',
+        })
+        .collect();
+    slug.trim_matches(&['-', '.'][..]).to_string()
+}
+
+fn release_page(changelog: &Changelog, release: &Release) -> ReleasePage {
+    let mut ids: Vec<String> = vec![];
+    let sections: Vec<SectionPage> = release
+        .sections
+        .iter()
+        .map(|section| SectionPage {
+            title: section.title.clone(),
+            kind: section.kind.map(|kind| kind.name()),
+            anchor: slug(&section.title),
+            description: changelog.html(&section.description, false),
+            entries: section
+                .entries
+                .iter()
+                .map(|entry| {
+                    // the same entry twice in a release gets distinct ids
+                    let mut id = entry.id();
+                    while ids.contains(&id) {
+                        id.push('-');
+                    }
+                    ids.push(id.clone());
+                    EntryPage {
+                        id,
+                        html: changelog.html(&entry.text, true),
+                        text: crate::schema::plain_text(&entry.text),
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+
+    let summary = sections
+        .iter()
+        .filter(|s| !s.entries.is_empty())
+        .map(|s| format!("{} {}", s.entries.len(), s.title.to_lowercase()))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    ReleasePage {
+        version: release.version.clone(),
+        date: release.date.clone(),
+        unreleased: release.unreleased,
+        yanked: release.yanked,
+        anchor: release.anchor(),
+        permalink: format!("releases/{}.html", slug(&release.version)),
+        summary,
+        description: changelog.html(&release.description, false),
+        sections,
+    }
+}
+
+/// Contents of a site file, from `templates` if it has one.
+fn template(templates: Option<&Path>, name: &str, default: &str) -> Result<String, ChangelogError> {
+    match templates.map(|dir| dir.join(name)) {
+        Some(path) if path.exists() => Ok(fs::read_to_string(path)?),
+        _ => Ok(default.to_string()),
+    }
+}
+
+fn template_error(name: &str, err: tinytemplate::error::Error) -> ChangelogError {
+    ChangelogError::Validation(format!("template {}: {}", name, err))
+}
+
+impl Changelog {
+    /// Writes the static site of the changelog into `out_dir`, with the
+    /// templates of the `templates` directory, if any, replacing the
+    /// default ones.
+    pub fn write_site(
+        &self,
+        out_dir: &Path,
+        templates: Option<&Path>,
+    ) -> Result<(), ChangelogError> {
+        let index = template(templates, "index.html", INDEX)?;
+        let release = template(templates, "release.html", RELEASE)?;
+        let style = template(templates, "style.css", STYLE)?;
+
+        let mut tt = TinyTemplate::new();
+        tt.add_template("index.html", &index)
+            .map_err(|err| template_error("index.html", err))?;
+        tt.add_template("release.html", &release)
+            .map_err(|err| template_error("release.html", err))?;
+
+        let title = crate::feed::title(&self.preamble).unwrap_or_else(|| String::from("Changelog"));
+        let releases: Vec<ReleasePage> = self
+            .releases
+            .iter()
+            .map(|release| release_page(self, release))
+            .collect();
+
+        fs::create_dir_all(out_dir.join("releases"))?;
+        let context = Index {
+            title: &title,
+            preamble: self.html(&self.preamble, false),
+            releases: &releases,
+        };
+        let html = tt
+            .render("index.html", &context)
+            .map_err(|err| template_error("index.html", err))?;
+        fs::write(out_dir.join("index.html"), html)?;
+        fs::write(out_dir.join("style.css"), style)?;
+
+        let mut search = vec![];
+        for release in &releases {
+            let context = Page {
+                title: &title,
+                release,
+            };
+            let html = tt
+                .render("release.html", &context)
+                .map_err(|err| template_error("release.html", err))?;
+            fs::write(out_dir.join(&release.permalink), html)?;
+
+            for section in &release.sections {
+                search.extend(section.entries.iter().map(|entry| SearchEntry {
+                    version: &release.version,
+                    section: &section.title,
+                    text: &entry.text,
+                    url: format!("{}#{}", release.permalink, entry.id),
+                }));
+            }
+        }
+        let search = serde_json::to_string(&search)
+            .map_err(|err| ChangelogError::Validation(err.to_string()))?;
+        fs::write(out_dir.join("search.json"), search)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,562 @@
+//! Plain text renderer that takes an iterator of events as input, for
+//! reading a changelog in a terminal.
+//!
+//! Paragraphs and list items are wrapped at the width of the output, links
+//! are numbered and listed as footnotes at the end of each release, and
+//! headings are underlined. With colors, releases are bold and the
+//! standard sections colored by kind.
+//!
+//! Widths are counted in terminal columns, so wide characters take two and
+//! combining ones none, and control characters of the changelog, which
+//! could drive the terminal, are replaced.
+
+use std::io::{self, Write};
+
+use pulldown_cmark::Event::*;
+use pulldown_cmark::{Event, HeadingLevel, LinkType, Tag};
+use unicode_width::UnicodeWidthChar;
+
+use crate::changelog::Kind;
+use crate::locale::Locale;
+use crate::render::{Output, StrWrite, WriteWrapper};
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const ITALIC: &str = "\x1b[3m";
+const UNDERLINE: &str = "\x1b[4m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const BLUE: &str = "\x1b[34m";
+const MAGENTA: &str = "\x1b[35m";
+const CYAN: &str = "\x1b[36m";
+const GRAY: &str = "\x1b[90m";
+const RESET: &str = "\x1b[0m";
+
+/// How text is laid out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextOptions {
+    /// Number of columns lines are wrapped at.
+    pub width: usize,
+
+    /// Whether to style the text with ANSI escape sequences.
+    pub color: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        TextOptions {
+            width: 80,
+            color: false,
+        }
+    }
+}
+
+/// Color of the heading of a section of `kind`.
+fn kind_color(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Added => GREEN,
+        Kind::Changed => YELLOW,
+        Kind::Deprecated => MAGENTA,
+        Kind::Removed => GRAY,
+        Kind::Fixed => CYAN,
+        Kind::Security => RED,
+    }
+}
+
+/// Number of columns taken by text, leaving out escape sequences.
+fn width(text: &str) -> usize {
+    let mut width = 0;
+    let mut escape = false;
+    for c in text.chars() {
+        match c {
+            '\x1b' => escape = true,
+            'm' if escape => escape = false,
+            _ if escape => (),
+            c => width += c.width().unwrap_or(0),
+        }
+    }
+    width
+}
+
+/// Text of the changelog with its control characters but tabs and
+/// newlines replaced, so that only the escape sequences of the renderer
+/// reach the terminal.
+fn printable(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\t' | '\n' => c,
+            c if c.is_control() => char::REPLACEMENT_CHARACTER,
+            c => c,
+        })
+        .collect()
+}
+
+/// Text without its escape sequences.
+fn strip(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut escape = false;
+    for c in text.chars() {
+        match c {
+            '\x1b' => escape = true,
+            'm' if escape => escape = false,
+            _ if escape => (),
+            c => stripped.push(c),
+        }
+    }
+    stripped
+}
+
+/// Lines of `text` no wider than `columns`, unless a word is; only
+/// newlines in `text` are kept.
+fn wrap(text: &str, columns: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        let mut line_width = 0;
+        for word in paragraph.split_whitespace() {
+            let word_width = width(word);
+            if line_width > 0 && line_width + 1 + word_width > columns {
+                lines.push(line);
+                line = String::new();
+                line_width = 0;
+            }
+            if line_width > 0 {
+                line.push(' ');
+                line_width += 1;
+            }
+            line.push_str(word);
+            line_width += word_width;
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+struct TextWriter<I, W> {
+    /// Iterator supplying events.
+    iter: I,
+
+    out: Output<W>,
+
+    options: TextOptions,
+
+    /// Locale telling the kinds of sections from their headings.
+    locale: &'static Locale,
+
+    /// Inline content of the current block, wrapped at its end.
+    block: String,
+
+    /// Level of the heading being written, if any.
+    heading: Option<HeadingLevel>,
+
+    /// Whether the events are in a code block, written as is.
+    in_code: bool,
+
+    /// Whether the last event was a block of raw HTML.
+    html_block: bool,
+
+    /// Styles of the open inline elements.
+    styles: Vec<&'static str>,
+
+    /// Next number of each open list, `None` for bullet lists.
+    lists: Vec<Option<u64>>,
+
+    /// Destination of each open link, `None` for those showing it.
+    links: Vec<Option<String>>,
+
+    /// Destinations of the links of the current release.
+    footnotes: Vec<String>,
+}
+
+impl<'a, I, W> TextWriter<I, W>
+where
+    I: Iterator<Item = Event<'a>>,
+    W: StrWrite,
+{
+    fn new(iter: I, writer: W, options: TextOptions, locale: &'static Locale) -> Self {
+        Self {
+            iter,
+            out: Output::new(writer),
+            options,
+            locale,
+            block: String::new(),
+            heading: None,
+            in_code: false,
+            html_block: false,
+            styles: vec![],
+            lists: vec![],
+            links: vec![],
+            footnotes: vec![],
+        }
+    }
+
+    /// Text in a style, if colors are on.
+    fn paint(&self, style: &str, text: &str) -> String {
+        if self.options.color {
+            format!("{}{}{}", style, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+
+    fn start_style(&mut self, style: &'static str) {
+        if self.options.color {
+            self.block.push_str(style);
+        }
+        self.styles.push(style);
+    }
+
+    /// Ends the innermost style, restoring those around it.
+    fn end_style(&mut self) {
+        self.styles.pop();
+        if self.options.color {
+            self.block.push_str(RESET);
+            self.block.push_str(&self.styles.concat());
+        }
+    }
+
+    /// Writes the inline content gathered so far, wrapped.
+    fn flush(&mut self) -> io::Result<()> {
+        let block = std::mem::take(&mut self.block);
+        if block.trim().is_empty() {
+            return Ok(());
+        }
+        let columns = self
+            .options
+            .width
+            .saturating_sub(self.out.indent_width())
+            .max(20);
+        self.out.start_block()?;
+        let lines = wrap(&block, columns);
+        self.out.write(&lines.join("\n"))
+    }
+
+    /// Writes the heading gathered so far, styled.
+    fn flush_heading(&mut self, level: HeadingLevel) -> io::Result<()> {
+        let text = strip(&std::mem::take(&mut self.block));
+        let text = text.trim();
+        self.out.start_block()?;
+        match level {
+            HeadingLevel::H1 | HeadingLevel::H2 => {
+                let style = if level == HeadingLevel::H1 {
+                    BOLD.to_string()
+                } else {
+                    format!("{}{}", BOLD, BLUE)
+                };
+                let underline = if level == HeadingLevel::H1 { "=" } else { "-" };
+                let heading = self.paint(&style, text);
+                let underline = self.paint(&style, &underline.repeat(width(text)));
+                self.out.write(&format!("{}\n{}", heading, underline))?;
+            }
+            _ => {
+                let style = match self.locale.kind(text) {
+                    Some(kind) => format!("{}{}", BOLD, kind_color(kind)),
+                    None => BOLD.to_string(),
+                };
+                let heading = self.paint(&style, text);
+                self.out.write(&heading)?;
+            }
+        }
+        self.out.end_block()
+    }
+
+    /// Lists the links of the release that just ended.
+    fn flush_footnotes(&mut self) -> io::Result<()> {
+        if self.footnotes.is_empty() {
+            return Ok(());
+        }
+        self.out.start_block()?;
+        let footnotes = std::mem::take(&mut self.footnotes);
+        for (i, url) in footnotes.iter().enumerate() {
+            let number = self.paint(DIM, &format!("[{}]", i + 1));
+            self.out.write(&format!("{} {}", number, printable(url)))?;
+            self.out.write_newline()?;
+        }
+        self.out.end_block()
+    }
+
+    pub fn run(mut self) -> io::Result<()> {
+        while let Some(event) = self.iter.next() {
+            if self.html_block && !matches!(event, Html(_)) {
+                self.html_block = false;
+                self.out.end_block()?;
+            }
+            match event {
+                Start(tag) => {
+                    self.start_tag(tag)?;
+                }
+                End(tag) => {
+                    self.end_tag(tag)?;
+                }
+                Text(text) => {
+                    if self.in_code {
+                        let code = self.paint(DIM, &printable(&text));
+                        self.out.write(&code)?;
+                    } else {
+                        self.block.push_str(&printable(&text));
+                    }
+                }
+                Code(text) => {
+                    let text = printable(&text);
+                    if self.options.color {
+                        let code = self.paint(YELLOW, &text);
+                        self.block.push_str(&code);
+                        self.block.push_str(&self.styles.concat());
+                    } else {
+                        self.block.push_str(&format!("`{}`", text));
+                    }
+                }
+                Html(html) => {
+                    let html = printable(&html);
+                    if html.ends_with('\n') {
+                        if !self.html_block {
+                            self.flush()?;
+                            self.out.start_block()?;
+                        }
+                        self.html_block = true;
+                        self.out.write(&html)?;
+                    } else {
+                        self.block.push_str(&html);
+                    }
+                }
+                SoftBreak => {
+                    self.block.push(' ');
+                }
+                HardBreak => {
+                    self.block.push('\n');
+                }
+                Rule => {
+                    self.flush()?;
+                    self.out.start_block()?;
+                    self.out.write("---")?;
+                    self.out.end_block()?;
+                }
+                FootnoteReference(name) => {
+                    self.block.push_str(&format!("[{}]", printable(&name)));
+                }
+                TaskListMarker(true) => {
+                    self.block.push_str("[x] ");
+                }
+                TaskListMarker(false) => {
+                    self.block.push_str("[ ] ");
+                }
+            }
+        }
+        self.flush()?;
+        self.flush_footnotes()
+    }
+
+    fn start_tag(&mut self, tag: Tag<'a>) -> io::Result<()> {
+        match tag {
+            Tag::Paragraph => self.flush(),
+            Tag::Heading(level, _, _) => {
+                self.flush()?;
+                if level == HeadingLevel::H2 {
+                    self.flush_footnotes()?;
+                }
+                self.heading = Some(level);
+                Ok(())
+            }
+            Tag::Table(_) | Tag::TableHead | Tag::TableRow => self.flush(),
+            Tag::TableCell => {
+                self.block.push_str("| ");
+                Ok(())
+            }
+            Tag::BlockQuote => {
+                self.flush()?;
+                self.out.start_block()?;
+                self.out.push_container("| ");
+                Ok(())
+            }
+            Tag::CodeBlock(_) => {
+                self.flush()?;
+                self.out.start_block()?;
+                self.out.push_container("    ");
+                self.in_code = true;
+                Ok(())
+            }
+            Tag::List(start) => {
+                self.flush()?;
+                self.out.start_block()?;
+                self.lists.push(start);
+                Ok(())
+            }
+            Tag::Item => {
+                self.flush()?;
+                self.out.end_line()?;
+                if self.out.end_block {
+                    self.out.write_newline()?;
+                    self.out.end_block = false;
+                }
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => String::from("* "),
+                };
+                self.out.write(&marker)?;
+                self.out.push_container(&" ".repeat(marker.chars().count()));
+                self.out.after_marker = true;
+                Ok(())
+            }
+            Tag::Emphasis => {
+                self.start_style(ITALIC);
+                Ok(())
+            }
+            Tag::Strong => {
+                self.start_style(BOLD);
+                Ok(())
+            }
+            Tag::Strikethrough => {
+                self.start_style(DIM);
+                Ok(())
+            }
+            Tag::Link(LinkType::Autolink, _, _) | Tag::Link(LinkType::Email, _, _) => {
+                self.links.push(None);
+                self.start_style(UNDERLINE);
+                Ok(())
+            }
+            Tag::Link(_, dest, _) | Tag::Image(_, dest, _) => {
+                self.links.push(Some(dest.to_string()));
+                self.start_style(UNDERLINE);
+                Ok(())
+            }
+            Tag::FootnoteDefinition(name) => {
+                self.flush()?;
+                self.block.push_str(&format!("[{}] ", printable(&name)));
+                Ok(())
+            }
+        }
+    }
+
+    fn end_tag(&mut self, tag: Tag) -> io::Result<()> {
+        match tag {
+            Tag::Heading(level, _, _) => {
+                self.heading = None;
+                self.flush_heading(level)?;
+            }
+            Tag::Paragraph | Tag::FootnoteDefinition(_) => {
+                self.flush()?;
+                self.out.end_block()?;
+            }
+            Tag::Table(_) => {
+                self.out.end_block()?;
+            }
+            Tag::TableHead | Tag::TableRow => {
+                self.flush()?;
+                self.out.end_line()?;
+            }
+            Tag::TableCell => {
+                self.block.push(' ');
+            }
+            Tag::BlockQuote => {
+                self.flush()?;
+                self.out.pop_container();
+                self.out.end_block()?;
+            }
+            Tag::CodeBlock(_) => {
+                self.in_code = false;
+                self.out.pop_container();
+                self.out.end_block()?;
+            }
+            Tag::List(_) => {
+                self.flush()?;
+                self.lists.pop();
+                self.out.end_line()?;
+                // a nested list does not make its parent loose
+                self.out.end_block = self.lists.is_empty();
+            }
+            Tag::Item => {
+                self.flush()?;
+                self.out.pop_container();
+                self.out.after_marker = false;
+                self.out.end_line()?;
+            }
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough => {
+                self.end_style();
+            }
+            Tag::Link(..) | Tag::Image(..) => {
+                self.end_style();
+                if let Some(Some(dest)) = self.links.pop() {
+                    // headings are only text
+                    if self.heading.is_none() {
+                        let number = match self.footnotes.iter().position(|url| *url == dest) {
+                            Some(i) => i + 1,
+                            None => {
+                                self.footnotes.push(dest);
+                                self.footnotes.len()
+                            }
+                        };
+                        let number = self.paint(DIM, &format!("[{}]", number));
+                        self.block.push_str(&number);
+                        self.block.push_str(&self.styles.concat());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Iterate over an `Iterator` of `Event`s, generate plain text for each
+/// `Event`, and write it out to a writable stream.
+pub(crate) fn write<'a, I, W>(
+    writer: W,
+    iter: I,
+    options: TextOptions,
+    locale: &'static Locale,
+) -> io::Result<()>
+where
+    I: Iterator<Item = Event<'a>>,
+    W: Write,
+{
+    TextWriter::new(iter, WriteWrapper(writer), options, locale).run()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pulldown_cmark::Parser;
+
+    fn text(markdown: &str, options: TextOptions) -> String {
+        let mut out = vec![];
+        write(&mut out, Parser::new(markdown), options, Locale::english()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn widths_in_columns() {
+        assert_eq!(width("abc"), 3);
+        assert_eq!(width("日本語"), 6);
+        assert_eq!(width("e\u{301}te\u{301}"), 3);
+        assert_eq!(width(&format!("{}bold{}", BOLD, RESET)), 4);
+    }
+
+    #[test]
+    fn wide_characters_wrap() {
+        let options = TextOptions {
+            width: 20,
+            color: false,
+        };
+        // five words of three characters would fit, but they take six
+        // columns each
+        assert_eq!(
+            text("日本語 日本語 日本語 日本語 日本語\n", options),
+            "日本語 日本語 日本語\n日本語 日本語\n"
+        );
+        assert_eq!(text("# 日本語\n", options), "日本語\n======\n");
+    }
+
+    #[test]
+    fn control_characters_are_replaced() {
+        let options = TextOptions::default();
+        assert_eq!(
+            text("a \x1b[2J b\x07\n\n    code\x1b]0;title\x07\n", options),
+            "a \u{fffd}[2J b\u{fffd}\n\n    code\u{fffd}]0;title\u{fffd}\n"
+        );
+        assert_eq!(text("`\x1b[31m`\n", options), "`\u{fffd}[31m`\n");
+    }
+}
@@ -0,0 +1,815 @@
+//! Versions of releases, and the schemes they follow.
+//!
+//! A version is read as numbers, a prerelease and build metadata, as in
+//! `1.2.3-rc.1+build.5`, with an optional `v` in front. Versions compare by
+//! precedence as [SemVer](https://semver.org/spec/v2.0.0.html#spec-item-11)
+//! defines it, whatever their scheme: numbers first, then a prerelease comes
+//! before its release, and build metadata does not count.
+//!
+//! A scheme tells which versions are valid: SemVer, CalVer like `2026.10.1`,
+//! or plain dotted numbers. Other schemes implement `VersionScheme`.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use regex::Regex;
+
+use crate::changelog::{Changelog, Release, Section, VersionStyle};
+use crate::error::ChangelogError;
+
+/// Identifier of a prerelease, e.g. `rc` and `1` in `1.0.0-rc.1`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Identifier {
+    Numeric(u64),
+    Alphanumeric(String),
+}
+
+impl Ord for Identifier {
+    /// Numeric identifiers come first, then alphanumeric ones in ASCII
+    /// order.
+    fn cmp(&self, other: &Identifier) -> Ordering {
+        match (self, other) {
+            (Identifier::Numeric(a), Identifier::Numeric(b)) => a.cmp(b),
+            (Identifier::Numeric(_), Identifier::Alphanumeric(_)) => Ordering::Less,
+            (Identifier::Alphanumeric(_), Identifier::Numeric(_)) => Ordering::Greater,
+            (Identifier::Alphanumeric(a), Identifier::Alphanumeric(b)) => a.cmp(b),
+        }
+    }
+}
+
+impl PartialOrd for Identifier {
+    fn partial_cmp(&self, other: &Identifier) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Identifier::Numeric(n) => write!(f, "{}", n),
+            Identifier::Alphanumeric(s) => f.write_str(s),
+        }
+    }
+}
+
+/// A version, written without its `v`.
+#[derive(Clone, Debug, Eq)]
+pub struct Version {
+    /// Numbers of the release, e.g. `[1, 2, 3]` for `1.2.3`.
+    pub numbers: Vec<u64>,
+
+    /// Identifiers of a prerelease, empty for a release.
+    pub prerelease: Vec<Identifier>,
+
+    /// Build metadata, which does not count in comparisons.
+    pub build: Option<String>,
+
+    /// Numbers as written, for schemes where `01` differs from `1`.
+    raw: Vec<String>,
+}
+
+impl Version {
+    /// Reads a version of any scheme: dotted numbers, then a prerelease
+    /// after `-` and build metadata after `+`, each dotted identifiers.
+    pub fn parse(version: &str) -> Option<Version> {
+        let version = match version.strip_prefix(&['v', 'V'][..]) {
+            Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest,
+            _ => version,
+        };
+        let (version, build) = match version.split_once('+') {
+            Some((version, build)) => (version, Some(build)),
+            None => (version, None),
+        };
+        let (numbers, prerelease) = match version.split_once('-') {
+            Some((numbers, prerelease)) => (numbers, Some(prerelease)),
+            None => (version, None),
+        };
+
+        let identifiers = |s: &str| -> bool {
+            s.split('.').all(|id| {
+                !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            })
+        };
+        if build.is_some_and(|build| !identifiers(build))
+            || prerelease.is_some_and(|pre| !identifiers(pre))
+        {
+            return None;
+        }
+
+        let raw: Vec<String> = numbers.split('.').map(String::from).collect();
+        let numbers = raw
+            .iter()
+            .map(|n| {
+                n.parse()
+                    .ok()
+                    .filter(|_| n.chars().all(|c| c.is_ascii_digit()))
+            })
+            .collect::<Option<Vec<u64>>>()?;
+        let prerelease = prerelease.map_or_else(Vec::new, |pre| {
+            pre.split('.')
+                .map(|id| match id.parse() {
+                    // `01` is kept as written, for SemVer to reject it
+                    Ok(n)
+                        if id.chars().all(|c| c.is_ascii_digit())
+                            && (id == "0" || !id.starts_with('0')) =>
+                    {
+                        Identifier::Numeric(n)
+                    }
+                    _ => Identifier::Alphanumeric(id.to_string()),
+                })
+                .collect()
+        });
+
+        Some(Version {
+            numbers,
+            prerelease,
+            build: build.map(String::from),
+            raw,
+        })
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.prerelease.is_empty()
+    }
+
+    /// Whether a number or a numeric identifier is written with a leading
+    /// zero, which SemVer does not allow.
+    fn has_leading_zero(&self) -> bool {
+        let zero = |s: &str| s.len() > 1 && s.starts_with('0');
+        self.raw.iter().any(|n| zero(n))
+            || self.prerelease.iter().any(|id| match id {
+                Identifier::Alphanumeric(s) => s.chars().all(|c| c.is_ascii_digit()) && zero(s),
+                Identifier::Numeric(_) => false,
+            })
+    }
+}
+
+impl Ord for Version {
+    /// Precedence: missing numbers count as zeros, so `1.2` is `1.2.0`.
+    fn cmp(&self, other: &Version) -> Ordering {
+        let len = self.numbers.len().max(other.numbers.len());
+        let number = |v: &Version, i: usize| v.numbers.get(i).copied().unwrap_or(0);
+        (0..len)
+            .map(|i| number(self, i).cmp(&number(other, i)))
+            .find(|ordering| *ordering != Ordering::Equal)
+            .unwrap_or_else(|| match (self.is_prerelease(), other.is_prerelease()) {
+                (false, false) => Ordering::Equal,
+                (false, true) => Ordering::Greater,
+                (true, false) => Ordering::Less,
+                (true, true) => self.prerelease.cmp(&other.prerelease),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Version) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Version) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.raw.join("."))?;
+        let prerelease: Vec<String> = self.prerelease.iter().map(|id| id.to_string()).collect();
+        if !prerelease.is_empty() {
+            write!(f, "-{}", prerelease.join("."))?;
+        }
+        if let Some(build) = &self.build {
+            write!(f, "+{}", build)?;
+        }
+        Ok(())
+    }
+}
+
+/// Which versions a project uses.
+pub trait VersionScheme: Sync {
+    /// Name of the scheme, as given on the command line.
+    fn name(&self) -> &'static str;
+
+    /// Reads a version of the scheme, with an optional `v` in front.
+    fn parse(&self, version: &str) -> Result<Version, ChangelogError>;
+}
+
+fn invalid(version: &str, scheme: &str) -> ChangelogError {
+    ChangelogError::Validation(format!("'{}' is not a {} version", version, scheme))
+}
+
+/// `MAJOR.MINOR.PATCH`, see [semver.org](https://semver.org).
+pub struct SemVer;
+
+impl VersionScheme for SemVer {
+    fn name(&self) -> &'static str {
+        "semver"
+    }
+
+    fn parse(&self, version: &str) -> Result<Version, ChangelogError> {
+        Version::parse(version)
+            .filter(|v| v.numbers.len() == 3 && !v.has_leading_zero())
+            .ok_or_else(|| invalid(version, self.name()))
+    }
+}
+
+/// A year, a month and an optional day or number, as in `2026.10` or
+/// `2026.10.1`; see [calver.org](https://calver.org). The year can be
+/// written short, `26.10`, and the month with a leading zero.
+pub struct CalVer;
+
+impl VersionScheme for CalVer {
+    fn name(&self) -> &'static str {
+        "calver"
+    }
+
+    fn parse(&self, version: &str) -> Result<Version, ChangelogError> {
+        Version::parse(version)
+            .filter(|v| {
+                (2..=3).contains(&v.numbers.len())
+                    && matches!(v.raw[0].len(), 2 | 4)
+                    && (1..=12).contains(&v.numbers[1])
+            })
+            .ok_or_else(|| invalid(version, self.name()))
+    }
+}
+
+/// Any number of dotted numbers, as in `1.2` or `1.2.3.4`.
+pub struct Dotted;
+
+impl VersionScheme for Dotted {
+    fn name(&self) -> &'static str {
+        "dotted"
+    }
+
+    fn parse(&self, version: &str) -> Result<Version, ChangelogError> {
+        Version::parse(version).ok_or_else(|| invalid(version, self.name()))
+    }
+}
+
+/// Built-in schemes, SemVer first.
+pub static SCHEMES: [&dyn VersionScheme; 3] = [&SemVer, &CalVer, &Dotted];
+
+/// Built-in scheme of a name.
+pub fn scheme(name: &str) -> Option<&'static dyn VersionScheme> {
+    SCHEMES
+        .iter()
+        .copied()
+        .find(|scheme| scheme.name().eq_ignore_ascii_case(name))
+}
+
+/// What becomes of the prereleases of a release once their entries are
+/// collapsed into it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PrereleaseMode {
+    /// They stay as they were.
+    Keep,
+
+    /// They are removed, with their link definitions.
+    Remove,
+
+    /// They are removed, and the release says which ones it follows.
+    Fold,
+}
+
+impl PrereleaseMode {
+    pub const ALL: [PrereleaseMode; 3] = [
+        PrereleaseMode::Keep,
+        PrereleaseMode::Remove,
+        PrereleaseMode::Fold,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PrereleaseMode::Keep => "keep",
+            PrereleaseMode::Remove => "remove",
+            PrereleaseMode::Fold => "fold",
+        }
+    }
+}
+
+impl FromStr for PrereleaseMode {
+    type Err = ChangelogError;
+
+    fn from_str(s: &str) -> Result<PrereleaseMode, ChangelogError> {
+        PrereleaseMode::ALL
+            .iter()
+            .copied()
+            .find(|mode| mode.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| ChangelogError::Validation(format!("unknown prerelease mode '{}'", s)))
+    }
+}
+
+/// Merges the entries of `from` into `into`, section by section, leaving
+/// out those `into` already has.
+fn merge_sections(into: &mut Vec<Section>, from: &[Section]) {
+    for section in from {
+        let position = into
+            .iter()
+            .position(|s| s.kind == section.kind && (s.kind.is_some() || s.title == section.title));
+        let target = match position {
+            Some(position) => &mut into[position],
+            None => {
+                into.push(Section {
+                    entries: vec![],
+                    ..section.clone()
+                });
+                into.last_mut().unwrap()
+            }
+        };
+        if !section.description.is_empty() && !target.description.contains(&section.description) {
+            if !target.description.is_empty() {
+                target.description.push_str("\n\n");
+            }
+            target.description.push_str(&section.description);
+        }
+        for entry in &section.entries {
+            if !target
+                .entries
+                .iter()
+                .any(|e| e.text.trim() == entry.text.trim())
+            {
+                target.entries.push(entry.clone());
+            }
+        }
+    }
+    // standard sections in their order, then the others
+    into.sort_by_key(|s| s.kind.map_or(usize::MAX, |kind| kind as usize));
+}
+
+impl Changelog {
+    /// Collapses the prereleases of release `version`, like `2.0.0-rc.1`
+    /// for `2.0.0`, into it: their entries come first, oldest first, then
+    /// its own, each once. The prereleases are then kept, removed or folded
+    /// as `mode` says.
+    pub fn collapse_prereleases(
+        &mut self,
+        version: &str,
+        scheme: &dyn VersionScheme,
+        mode: PrereleaseMode,
+    ) -> Result<(), ChangelogError> {
+        lazy_static! {
+            static ref COMPARE_PATTERN: Regex =
+                Regex::new(r"^(?P<base>.*/compare/)(?P<from>.+?)\.\.\.(?P<to>.+)$").unwrap();
+        }
+
+        let final_version = scheme.parse(version)?;
+        if final_version.is_prerelease() {
+            return Err(ChangelogError::Validation(format!(
+                "{} is a prerelease itself",
+                version
+            )));
+        }
+        let position = self
+            .releases
+            .iter()
+            .position(|r| !r.unreleased && r.version == version)
+            .ok_or_else(|| ChangelogError::NotFound(format!("release {} not found", version)))?;
+
+        // prereleases of the version, newest first as in the file
+        let prereleases: Vec<usize> = (0..self.releases.len())
+            .filter(|&i| {
+                let release = &self.releases[i];
+                !release.unreleased
+                    && scheme
+                        .parse(&release.version)
+                        .is_ok_and(|v| v.is_prerelease() && v.numbers == final_version.numbers)
+            })
+            .collect();
+
+        let mut sections = vec![];
+        let mut descriptions = vec![];
+        for &i in prereleases.iter().rev() {
+            merge_sections(&mut sections, &self.releases[i].sections);
+            descriptions.push(self.releases[i].description.clone());
+        }
+        merge_sections(&mut sections, &self.releases[position].sections);
+        self.releases[position].sections = sections;
+        if mode == PrereleaseMode::Keep {
+            return Ok(());
+        }
+
+        let collapsed: Vec<Release> = prereleases
+            .iter()
+            .rev()
+            .map(|&i| self.releases[i].clone())
+            .collect();
+        let release = &mut self.releases[position];
+        for description in descriptions.iter().filter(|d| !d.is_empty()) {
+            if !release.description.contains(description.as_str()) {
+                if !release.description.is_empty() {
+                    release.description.push_str("\n\n");
+                }
+                release.description.push_str(description);
+            }
+        }
+        if mode == PrereleaseMode::Fold && !collapsed.is_empty() {
+            let list: Vec<String> = collapsed
+                .iter()
+                .map(|r| match &r.date {
+                    Some(date) => format!("{} ({})", r.version, date),
+                    None => r.version.clone(),
+                })
+                .collect();
+            if !release.description.is_empty() {
+                release.description.push_str("\n\n");
+            }
+            release
+                .description
+                .push_str(&format!("Follows the prereleases {}.", list.join(", ")));
+        }
+
+        // the compare link of the release starts where its first
+        // prerelease's did
+        let first = collapsed
+            .first()
+            .and_then(|r| self.release_url(r))
+            .and_then(|url| COMPARE_PATTERN.captures(url).map(|c| c["from"].to_string()));
+        let url = match &mut self.releases[position].style.version {
+            VersionStyle::Linked(url) => Some(url),
+            _ => self
+                .links
+                .iter_mut()
+                .find(|(l, _)| l.eq_ignore_ascii_case(version))
+                .map(|(_, url)| url),
+        };
+        if let (Some(from), Some(url)) = (first, url) {
+            if let Some(captures) = COMPARE_PATTERN.captures(url) {
+                *url = format!("{}{}...{}", &captures["base"], from, &captures["to"]);
+            }
+        }
+        self.links.retain(|(label, _)| {
+            !collapsed
+                .iter()
+                .any(|r| label.eq_ignore_ascii_case(&r.version))
+        });
+        self.releases.retain(|r| {
+            r.unreleased
+                || !collapsed
+                    .iter()
+                    .any(|c| !c.unreleased && c.version == r.version)
+        });
+        Ok(())
+    }
+
+    /// Release of the highest version of `scheme` that is not yanked, where
+    /// `latest` gives the most recent one. Prereleases are left out unless
+    /// `prereleases` is set, and so are versions not of the scheme.
+    pub fn latest_version(
+        &self,
+        scheme: &dyn VersionScheme,
+        prereleases: bool,
+    ) -> Option<&Release> {
+        self.releases
+            .iter()
+            .filter(|r| !r.unreleased && !r.yanked)
+            .filter_map(|r| scheme.parse(&r.version).ok().map(|version| (version, r)))
+            .filter(|(version, _)| prereleases || !version.is_prerelease())
+            // the first of equal versions, as `max_by` would take the last
+            .fold(
+                None,
+                |latest: Option<(Version, &Release)>, (version, r)| match latest {
+                    Some((max, _)) if version > max => Some((version, r)),
+                    None => Some((version, r)),
+                    latest => latest,
+                },
+            )
+            .map(|(_, r)| r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::changelog::Kind;
+
+    fn semver(version: &str) -> Version {
+        SemVer.parse(version).unwrap()
+    }
+
+    #[test]
+    fn semver_precedence() {
+        let versions = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-beta",
+            "1.0.0-rc.1",
+            "1.0.0",
+        ];
+        for pair in versions.windows(2) {
+            assert!(
+                semver(pair[0]) < semver(pair[1]),
+                "{} < {}",
+                pair[0],
+                pair[1]
+            );
+        }
+        assert!(semver("1.0.0-alpha.beta") > semver("1.0.0-alpha.1"));
+        assert!(semver("1.0.0-beta.11") > semver("1.0.0-beta.2"));
+        assert!(semver("2.1.1") > semver("2.1.0"));
+        assert!(semver("1.10.0") > semver("1.9.0"));
+        assert_eq!(semver("1.0.0+build.1"), semver("1.0.0+build.2"));
+    }
+
+    #[test]
+    fn semver_rejects_leading_zeros() {
+        assert!(SemVer.parse("01.0.0").is_err());
+        assert!(SemVer.parse("1.02.0").is_err());
+        assert!(SemVer.parse("1.0.0-rc.01").is_err());
+        assert!(SemVer.parse("1.0.0-rc.0").is_ok());
+        assert!(SemVer.parse("1.0.0-0a").is_ok());
+    }
+
+    #[test]
+    fn semver_needs_three_numbers() {
+        assert!(SemVer.parse("1.0").is_err());
+        assert!(SemVer.parse("1.0.0.0").is_err());
+        assert!(SemVer.parse("1.0.0-").is_err());
+        assert!(SemVer.parse("1.0.0-rc..1").is_err());
+        assert!(SemVer.parse("1.0.0+").is_err());
+    }
+
+    #[test]
+    fn calver_month_bounds() {
+        assert!(CalVer.parse("2026.1").is_ok());
+        assert!(CalVer.parse("2026.01").is_ok());
+        assert!(CalVer.parse("2026.12.3").is_ok());
+        assert!(CalVer.parse("26.10").is_ok());
+        assert!(CalVer.parse("2026.0").is_err());
+        assert!(CalVer.parse("2026.13").is_err());
+        assert!(CalVer.parse("202.10").is_err());
+        assert!(CalVer.parse("2026").is_err());
+        assert!(CalVer.parse("2026.10.1.1").is_err());
+    }
+
+    #[test]
+    fn dotted_versions() {
+        assert!(Dotted.parse("1").is_ok());
+        assert!(Dotted.parse("1.2.3.4").is_ok());
+        assert!(Dotted.parse("1.x").is_err());
+        assert_eq!(Dotted.parse("1.2").unwrap(), Dotted.parse("1.2.0").unwrap());
+    }
+
+    #[test]
+    fn v_prefix_is_dropped() {
+        assert_eq!(semver("v1.2.3").to_string(), "1.2.3");
+        assert_eq!(semver("V1.2.3-rc.1+b5").to_string(), "1.2.3-rc.1+b5");
+        assert_eq!(semver("v1.2.3"), semver("1.2.3"));
+        assert!(SemVer.parse("vv1.2.3").is_err());
+        assert!(SemVer.parse("version1.2.3").is_err());
+    }
+
+    #[test]
+    fn schemes_by_name() {
+        assert_eq!(scheme("semver").map(|s| s.name()), Some("semver"));
+        assert_eq!(scheme("CalVer").map(|s| s.name()), Some("calver"));
+        assert!(scheme("romver").is_none());
+    }
+
+    #[test]
+    fn latest_skips_prereleases_and_yanked_releases() {
+        let changelog = Changelog::parse(
+            "# Changelog\n\n\
+             ## [Unreleased]\n\n\
+             ## [2.0.0-rc.1] - 2020-04-01\n\n\
+             ## [1.2.0] - 2020-03-01 [YANKED]\n\n\
+             ## [1.1.0] - 2020-02-01\n\n\
+             ## [not-a-version] - 2020-01-15\n\n\
+             ## [1.0.0] - 2020-01-01\n",
+        )
+        .unwrap();
+
+        let latest = |prereleases| {
+            changelog
+                .latest_version(&SemVer, prereleases)
+                .map(|r| r.version.as_str())
+        };
+        assert_eq!(latest(false), Some("1.1.0"));
+        assert_eq!(latest(true), Some("2.0.0-rc.1"));
+    }
+
+    #[test]
+    fn latest_of_nothing() {
+        let changelog = Changelog::parse("# Changelog\n\n## [Unreleased]\n").unwrap();
+        assert!(changelog.latest_version(&SemVer, true).is_none());
+    }
+
+    const PRERELEASES: &str = "# Changelog
+
+## [Unreleased]
+
+## [2.0.0] - 2020-06-01
+
+### Added
+
+- Feature B
+
+### Fixed
+
+- Last fix
+
+## [2.0.0-rc.2] - 2020-05-10
+
+Second candidate.
+
+### Added
+
+- Feature B
+
+### Fixed
+
+- Crash in rc.1
+
+## [2.0.0-rc.1] - 2020-05-01
+
+Big rewrite.
+
+### Added
+
+- Feature A
+
+## [1.0.0] - 2020-01-01
+
+### Added
+
+- Initial
+
+[Unreleased]: https://github.com/o/r/compare/v2.0.0...HEAD
+[2.0.0]: https://github.com/o/r/compare/v2.0.0-rc.2...v2.0.0
+[2.0.0-rc.2]: https://github.com/o/r/compare/v2.0.0-rc.1...v2.0.0-rc.2
+[2.0.0-rc.1]: https://github.com/o/r/compare/v1.0.0...v2.0.0-rc.1
+[1.0.0]: https://github.com/o/r/releases/tag/v1.0.0
+";
+
+    fn collapsed(mode: PrereleaseMode) -> Changelog {
+        let mut changelog = Changelog::parse(PRERELEASES).unwrap();
+        changelog
+            .collapse_prereleases("2.0.0", &SemVer, mode)
+            .unwrap();
+        changelog
+    }
+
+    fn entries(release: &Release, kind: Kind) -> Vec<&str> {
+        release
+            .section(kind)
+            .unwrap()
+            .entries
+            .iter()
+            .map(|e| e.text.as_str())
+            .collect()
+    }
+
+    fn versions(changelog: &Changelog) -> Vec<&str> {
+        changelog
+            .releases
+            .iter()
+            .map(|r| r.version.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn collapse_merges_oldest_first_once() {
+        for mode in PrereleaseMode::ALL.iter().copied() {
+            let changelog = collapsed(mode);
+            let release = changelog.release_by_version("2.0.0").unwrap();
+            assert_eq!(
+                entries(release, Kind::Added),
+                ["Feature A", "Feature B"],
+                "{:?}",
+                mode
+            );
+            assert_eq!(
+                entries(release, Kind::Fixed),
+                ["Crash in rc.1", "Last fix"],
+                "{:?}",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn collapse_and_keep() {
+        let changelog = collapsed(PrereleaseMode::Keep);
+        let original = Changelog::parse(PRERELEASES).unwrap();
+        assert_eq!(versions(&changelog), versions(&original));
+        assert_eq!(changelog.links, original.links);
+        assert_eq!(
+            changelog.release_by_version("2.0.0").unwrap().description,
+            ""
+        );
+        assert_eq!(
+            changelog.release_by_version("2.0.0-rc.1"),
+            original.release_by_version("2.0.0-rc.1")
+        );
+    }
+
+    #[test]
+    fn collapse_and_remove() {
+        let changelog = collapsed(PrereleaseMode::Remove);
+        assert_eq!(versions(&changelog), ["Unreleased", "2.0.0", "1.0.0"]);
+        assert_eq!(
+            changelog.release_by_version("2.0.0").unwrap().description,
+            "Big rewrite.\n\nSecond candidate."
+        );
+        let labels: Vec<&str> = changelog
+            .links
+            .iter()
+            .map(|(label, _)| label.as_str())
+            .collect();
+        assert_eq!(labels, ["Unreleased", "2.0.0", "1.0.0"]);
+        // the compare link now starts where the first prerelease's did
+        assert_eq!(
+            changelog.link("2.0.0"),
+            Some("https://github.com/o/r/compare/v1.0.0...v2.0.0")
+        );
+        assert_eq!(
+            changelog.link("Unreleased"),
+            Some("https://github.com/o/r/compare/v2.0.0...HEAD")
+        );
+    }
+
+    #[test]
+    fn collapse_and_fold() {
+        let changelog = collapsed(PrereleaseMode::Fold);
+        assert_eq!(versions(&changelog), ["Unreleased", "2.0.0", "1.0.0"]);
+        assert_eq!(
+            changelog.release_by_version("2.0.0").unwrap().description,
+            "Big rewrite.\n\nSecond candidate.\n\n\
+             Follows the prereleases 2.0.0-rc.1 (2020-05-01), 2.0.0-rc.2 (2020-05-10)."
+        );
+        assert_eq!(
+            changelog.link("2.0.0"),
+            Some("https://github.com/o/r/compare/v1.0.0...v2.0.0")
+        );
+    }
+
+    #[test]
+    fn collapse_writes_back_the_same() {
+        for mode in PrereleaseMode::ALL.iter().copied() {
+            let changelog = collapsed(mode);
+            assert_eq!(
+                Changelog::parse(&changelog.to_string()).unwrap(),
+                changelog,
+                "{:?}",
+                mode
+            );
+        }
+    }
+
+    #[test]
+    fn collapse_linked_headings() {
+        // the same links, written in the headings
+        let original = Changelog::parse(PRERELEASES).unwrap();
+        let mut input = String::new();
+        for line in PRERELEASES.lines().filter(|l| !l.starts_with('[')) {
+            let line = match line.strip_prefix("## [") {
+                Some(heading) => {
+                    let (version, rest) = heading.split_once(']').unwrap();
+                    let url = original.link(version).unwrap();
+                    format!("## [{}]({}){}", version, url, rest)
+                }
+                None => line.to_string(),
+            };
+            input.push_str(&line);
+            input.push('\n');
+        }
+        let mut changelog = Changelog::parse(&input).unwrap();
+        assert!(changelog.links.is_empty());
+        changelog
+            .collapse_prereleases("2.0.0", &SemVer, PrereleaseMode::Remove)
+            .unwrap();
+        let release = changelog.release_by_version("2.0.0").unwrap();
+        assert_eq!(
+            changelog.release_url(release),
+            Some("https://github.com/o/r/compare/v1.0.0...v2.0.0")
+        );
+    }
+
+    #[test]
+    fn collapse_without_compare_links() {
+        let input =
+            PRERELEASES.replace("/compare/v1.0.0...v2.0.0-rc.1", "/releases/tag/v2.0.0-rc.1");
+        let mut changelog = Changelog::parse(&input).unwrap();
+        changelog
+            .collapse_prereleases("2.0.0", &SemVer, PrereleaseMode::Remove)
+            .unwrap();
+        assert_eq!(
+            changelog.link("2.0.0"),
+            Some("https://github.com/o/r/compare/v2.0.0-rc.2...v2.0.0")
+        );
+    }
+
+    #[test]
+    fn collapse_errors() {
+        let mut changelog = Changelog::parse(PRERELEASES).unwrap();
+        let prerelease =
+            changelog.collapse_prereleases("2.0.0-rc.2", &SemVer, PrereleaseMode::Fold);
+        assert!(matches!(prerelease, Err(ChangelogError::Validation(_))));
+        let missing = changelog.collapse_prereleases("3.0.0", &SemVer, PrereleaseMode::Fold);
+        assert!(matches!(missing, Err(ChangelogError::NotFound(_))));
+        assert_eq!(changelog, Changelog::parse(PRERELEASES).unwrap());
+    }
+}
@@ -0,0 +1,78 @@
+//! Inputs that once crashed the fuzz targets, replayed with the same checks.
+//!
+//! Files in `fuzz/regressions` are named after the target that found them,
+//! as `cargo fuzz` writes them in `fuzz/artifacts`.
+
+use std::fs;
+use std::path::Path;
+use std::str;
+
+use changelog::{Changelog, Kind};
+
+/// The input as the fuzz targets see it: its longest UTF-8 prefix.
+fn text(data: &[u8]) -> &str {
+    match str::from_utf8(data) {
+        Ok(text) => text,
+        Err(err) => str::from_utf8(&data[..err.valid_up_to()]).unwrap(),
+    }
+}
+
+fn assert_roundtrip(changelog: &Changelog) {
+    let written = changelog.to_string();
+    let reparsed = Changelog::parse(&written).expect("written changelog does not parse");
+    assert_eq!(changelog, &reparsed, "written:\n{}", written);
+}
+
+fn parse(input: &str) {
+    let _ = Changelog::parse(input);
+}
+
+fn roundtrip(input: &str) {
+    if let Ok(changelog) = Changelog::parse(input) {
+        assert_roundtrip(&changelog);
+    }
+}
+
+fn add_entry(input: &str) {
+    let mut parts = input.splitn(2, '\0');
+    let (document, text) = match (parts.next(), parts.next()) {
+        (Some(document), Some(text)) => (document, text),
+        _ => return,
+    };
+    let kind = Kind::ALL[text.len() % Kind::ALL.len()];
+
+    if let Ok(mut changelog) = Changelog::parse(document) {
+        if changelog.add_entry(kind, text).is_ok() {
+            assert_roundtrip(&changelog);
+        }
+    }
+}
+
+#[test]
+fn fuzz_regressions() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/regressions");
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        let data = fs::read(&path).unwrap();
+
+        match name.split('-').next() {
+            Some("parse") => parse(text(&data)),
+            Some("roundtrip") => roundtrip(text(&data)),
+            Some("add_entry") => add_entry(text(&data)),
+            _ => panic!("no fuzz target for {}", name),
+        }
+    }
+}
+
+#[test]
+fn empty_heading_at_end() {
+    assert!(Changelog::parse("# Changelog\n\n## ").is_err());
+    roundtrip("# Changelog\n\n## [1.0.0]\n\n### ");
+}
+
+#[test]
+fn heading_with_only_a_link() {
+    roundtrip("# Changelog\n\n## [1.0.0](https://example.com)\n");
+    roundtrip("# Changelog\n\n## [1.0.0]\n\n### [Added](https://example.com)\n");
+}